@@ -1,26 +1,42 @@
+use aoc2019::fuel::{self, ModuleFuel, ReportFormat};
+use std::env;
 use std::io::{self, Read};
+use std::process;
 
+// Usage: day01 [--report table|csv] < input
 fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let report = match args.as_slice() {
+        [] => None,
+        [flag, format] if flag == "--report" => {
+            Some(format.parse::<ReportFormat>().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            }))
+        }
+        _ => {
+            eprintln!("usage: day01 [--report table|csv] < input");
+            process::exit(1);
+        }
+    };
+
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
+    let masses = fuel::parse_masses(&input).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
 
-    let sum1: u32 = input
-        .lines()
-        .map(|x| x.parse::<u32>().unwrap() / 3 - 2)
-        .sum();
-
-    println!("p1: {}", sum1);
-
-    let mut sum2: i32 = 0;
-    for val in input.lines() {
-        let mut v = val.parse::<i32>().unwrap() / 3 - 2;
-        while v > 0 {
-            sum2 += v;
-            v = v / 3 - 2;
-        }
+    if let Some(format) = report {
+        let modules: Vec<ModuleFuel> = masses.iter().map(|m| ModuleFuel::new(*m)).collect();
+        return fuel::write_report(&modules, format, &mut io::stdout());
     }
 
-    println!("p2: {}", sum2);
+    println!("p1: {}", masses.iter().map(|m| fuel::fuel(*m)).sum::<u64>());
+    println!(
+        "p2: {}",
+        masses.iter().map(|m| fuel::total_fuel(*m)).sum::<u64>()
+    );
 
     Ok(())
 }
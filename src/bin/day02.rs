@@ -1,3 +1,4 @@
+use aoc2019::intcode::symbolic::{Expr, SymbolicCpu};
 use std::io;
 
 #[derive(PartialEq)]
@@ -65,6 +66,31 @@ fn solve_p2(input: Vec<usize>) -> usize {
     0
 }
 
+// Runs the program with symbolic noun and verb. If the result is linear in both, the verb can
+// be computed for every noun instead of running the program 10000 times.
+fn solve_p2_symbolic(input: &[usize]) -> Option<usize> {
+    let program: Vec<i64> = input.iter().map(|x| *x as i64).collect();
+    let mut cpu = SymbolicCpu::new(&program);
+    cpu.set_cell(1, Expr::sym("noun"));
+    cpu.set_cell(2, Expr::sym("verb"));
+    let paths = cpu.run();
+    let (c, coeffs) = match paths.as_slice() {
+        [path] => path.memory[0].linear()?,
+        _ => return None,
+    };
+    let a = coeffs.get("noun").copied().unwrap_or(0);
+    let b = coeffs.get("verb").copied().unwrap_or(0);
+    for noun in 0..=99 {
+        let rest = 19_690_720 - c - a * noun;
+        for verb in 0..=99 {
+            if b * verb == rest {
+                return Some((100 * noun + verb) as usize);
+            }
+        }
+    }
+    None
+}
+
 fn main() -> io::Result<()> {
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
@@ -76,7 +102,8 @@ fn main() -> io::Result<()> {
         .collect();
 
     println!("p1: {}", solve_p1(input.clone()));
-    println!("p2: {}", solve_p2(input.clone()));
+    let p2 = solve_p2_symbolic(&input).unwrap_or_else(|| solve_p2(input.clone()));
+    println!("p2: {}", p2);
 
     Ok(())
 }
@@ -103,4 +130,15 @@ mod tests {
         run_until_halt(&mut v);
         assert_eq!(v, [30, 1, 1, 4, 2, 5, 6, 0, 99]);
     }
+
+    #[test]
+    fn test_solve_p2_symbolic() {
+        let input: Vec<usize> = std::fs::read_to_string("./input/day02.in")
+            .unwrap()
+            .trim()
+            .split(',')
+            .map(|x| x.parse::<usize>().unwrap())
+            .collect();
+        assert_eq!(solve_p2_symbolic(&input), Some(solve_p2(input)));
+    }
 }
@@ -1,88 +1,42 @@
-use std::collections::{HashMap, HashSet};
-use std::io;
-
-fn calc_distance(pos1: (i32, i32), pos2: (i32, i32)) -> u32 {
-    ((pos1.0 - pos2.0).abs() + (pos1.1 - pos2.1).abs()) as u32
-}
-
-fn get_route(route: &mut HashMap<(i32, i32), u32>, route_raw: &[&str]) {
-    let mut cur_pos = (0, 0);
-    let mut total_dist = 0;
-    for dir in route_raw {
-        let mut dist = dir[1..].parse::<i32>().unwrap();
-        match &dir[..1] {
-            "L" => {
-                while dist != 0 {
-                    cur_pos.0 -= 1;
-                    total_dist += 1;
-                    route.insert(cur_pos, total_dist);
-                    dist -= 1;
-                }
-            }
-            "U" => {
-                while dist != 0 {
-                    cur_pos.1 += 1;
-                    total_dist += 1;
-                    route.insert(cur_pos, total_dist);
-                    dist -= 1;
-                }
-            }
-            "R" => {
-                while dist != 0 {
-                    cur_pos.0 += 1;
-                    total_dist += 1;
-                    route.insert(cur_pos, total_dist);
-                    dist -= 1;
-                }
-            }
-            "D" => {
-                while dist != 0 {
-                    cur_pos.1 -= 1;
-                    total_dist += 1;
-                    route.insert(cur_pos, total_dist);
-                    dist -= 1;
-                }
-            }
-            _ => {
-                panic!("unknown direction: {}", &dir[..1]);
-            }
-        };
-    }
-}
+use aoc2019::wires;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Read};
+use std::process;
 
+// Usage: day03 [--svg FILE] < input
 fn main() -> io::Result<()> {
-    let mut wire1_input = String::new();
-    let mut wire2_input = String::new();
-    io::stdin().read_line(&mut wire1_input)?;
-    io::stdin().read_line(&mut wire2_input)?;
-
-    let wire1: Vec<&str> = wire1_input.trim().split(',').collect();
-    let wire2: Vec<&str> = wire2_input.trim().split(',').collect();
-
-    let mut wire1_map: HashMap<(i32, i32), u32> = HashMap::new();
-    get_route(&mut wire1_map, &wire1);
-    let mut wire2_map: HashMap<(i32, i32), u32> = HashMap::new();
-    get_route(&mut wire2_map, &wire2);
-
-    let wire1_set: HashSet<(i32, i32)> = wire1_map.keys().copied().collect();
-    let wire2_set: HashSet<(i32, i32)> = wire2_map.keys().copied().collect();
-
-    let is = wire1_set.intersection(&wire2_set);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let svg = match args.as_slice() {
+        [] => None,
+        [flag, path] if flag == "--svg" => Some(path),
+        _ => {
+            eprintln!("usage: day03 [--svg FILE] < input");
+            process::exit(1);
+        }
+    };
+
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    let wires = wires::parse_wires(&input).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let crossings = wires::crossings(&wires);
+    if let Some(path) = svg {
+        wires::write_svg(&wires, &crossings, &mut BufWriter::new(File::create(path)?))?;
+    }
+    if crossings.is_empty() {
+        eprintln!("the wires do not cross");
+        process::exit(1);
+    }
 
     println!(
         "p1: {}",
-        is.clone()
-            .map(|ele| calc_distance((0, 0), *ele))
-            .min()
-            .unwrap()
-    );
-
-    println!(
-        "p2: {}",
-        is.map(|ele| wire1_map.get(&ele).unwrap() + wire2_map.get(&ele).unwrap())
-            .min()
-            .unwrap()
+        crossings.iter().map(|c| c.distance).min().unwrap()
     );
+    println!("p2: {}", crossings.iter().map(|c| c.delay).min().unwrap());
 
     Ok(())
 }
@@ -1,76 +1,79 @@
-use std::iter::from_fn;
+use aoc2019::passwords::Rules;
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
 
-fn digits_inc_or_same(num: u32) -> bool {
-    let mut it = rev_digit_iter(num);
-    let mut lastd = it.next().unwrap();
-    for d in it {
-        if lastd < d {
-            return false;
-        }
-        lastd = d;
+fn parse_range(s: &str) -> Option<(u64, u64)> {
+    let mut parts = s.trim().splitn(2, '-');
+    let lo = parts.next()?.parse::<u64>().ok()?;
+    let hi = parts.next()?.parse::<u64>().ok()?;
+    if lo > hi || hi >= 1_000_000_000_000_000_000 {
+        return None;
     }
-    true
+    Some((lo, hi))
 }
 
-fn adjacent_digits_same(num: u32) -> bool {
-    let mut it = rev_digit_iter(num);
-    let mut lastd = it.next().unwrap();
-    for d in it {
-        if lastd == d {
-            return true;
-        }
-        lastd = d;
-    }
-    false
+fn usage() -> ! {
+    eprintln!("usage: day04 [LO-HI] [--rules SPEC] [--check PASSWORD]");
+    process::exit(1);
 }
 
-fn adjacent_digits_same_advanced(num: u32) -> bool {
-    let mut count = 1;
-
-    let mut it = rev_digit_iter(num);
-    let mut lastd = it.next().unwrap();
-    for d in it {
-        if lastd == d {
-            count += 1;
-        } else {
-            if count == 2 {
-                return true;
+// Usage: day04 [LO-HI] [--rules SPEC] [--check PASSWORD]
+// Counts the passwords in the range, read from input/day04.in if not given, or checks a single password.
+// Without --rules the rule sets of both parts are used.
+fn main() -> io::Result<()> {
+    let mut range = None;
+    let mut check = None;
+    let mut rule_sets = vec![("p1", Rules::part1()), ("p2", Rules::part2())];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => {
+                let password = args.next().and_then(|p| p.parse::<u64>().ok());
+                check = Some(password.unwrap_or_else(|| usage()));
+            }
+            "--rules" => {
+                let rules = args.next().unwrap_or_else(|| usage()).parse::<Rules>();
+                rule_sets = vec![(
+                    "rules",
+                    rules.unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        process::exit(1);
+                    }),
+                )];
             }
-            count = 1;
+            _ if range.is_none() => range = Some(arg),
+            _ => usage(),
         }
-        lastd = d;
     }
 
-    if count == 2 {
-        return true;
-    }
-
-    false
-}
-
-fn rev_digit_iter(mut num: u32) -> impl Iterator<Item = u32> {
-    from_fn(move || {
-        if num == 0 {
-            None
-        } else {
-            let d = num % 10;
-            num /= 10;
-            Some(d)
+    if let Some(password) = check {
+        for (name, rules) in &rule_sets {
+            match rules.check(password) {
+                Ok(()) => println!("{}: valid", name),
+                Err(reason) => println!("{}: invalid, {}", name, reason),
+            }
         }
-    })
-}
-
-fn main() {
-    let p1 = (138_241..=674_034)
-        .filter(|&x| digits_inc_or_same(x) && adjacent_digits_same(x))
-        .count();
+        return Ok(());
+    }
 
-    let p2 = (138_241..=674_034)
-        .filter(|&x| digits_inc_or_same(x) && adjacent_digits_same_advanced(x))
-        .count();
+    let range = match range {
+        Some(range) => range,
+        None => fs::read_to_string("./input/day04.in")?,
+    };
+    let (lo, hi) = parse_range(&range).unwrap_or_else(|| {
+        eprintln!(
+            "invalid range {:?}, expected LO-HI with up to 18 digits",
+            range.trim()
+        );
+        process::exit(1);
+    });
 
-    println!("p1: {}", p1);
-    println!("p2: {}", p2);
+    for (name, rules) in &rule_sets {
+        println!("{}: {}", name, rules.count(lo, hi));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -78,24 +81,9 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_digits_inc_or_same() {
-        assert_eq!(digits_inc_or_same(111_111), true);
-        assert_eq!(digits_inc_or_same(223_450), false);
-    }
-
-    #[test]
-    fn test_two_adjacent_digits_same() {
-        assert_eq!(adjacent_digits_same(122_345), true);
-        assert_eq!(adjacent_digits_same(111_111), true);
-    }
-
-    #[test]
-    fn test_adjacent_digits_same_advanced() {
-        assert_eq!(adjacent_digits_same_advanced(122_345), true);
-        assert_eq!(adjacent_digits_same_advanced(111_122), true);
-        assert_eq!(adjacent_digits_same_advanced(123_444), false);
-        assert_eq!(adjacent_digits_same_advanced(112_233), true);
-        assert_eq!(adjacent_digits_same_advanced(111_111), false);
-        assert_eq!(adjacent_digits_same_advanced(112_222), true);
+    fn test_parse_range() {
+        assert_eq!(parse_range("1-1000000000000000000"), None);
+        assert_eq!(parse_range("10-5"), None);
+        assert_eq!(parse_range("138241-674034\n"), Some((138_241, 674_034)));
     }
 }
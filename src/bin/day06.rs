@@ -1,73 +1,88 @@
-use std::collections::HashMap;
-use std::fs;
+use aoc2019::orbits::{self, DotOptions};
+use std::env;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::process;
 
-fn solve_p1(edges: &[(&str, &str)]) -> usize {
-    let mut orbit_map: HashMap<&str, &str> = HashMap::new();
-    for (orbited, orbiter) in edges {
-        orbit_map.insert(orbiter, orbited);
-    }
+const USAGE: &str = "usage: day06 [FROM TO] [--path] [--dot FILE [--highlight] [--collapse]]";
 
-    let mut count = 0;
-    for (_, orbiter) in edges {
-        let mut orbited = orbiter;
-        while orbit_map.contains_key(orbited) {
-            count += 1;
-            orbited = orbit_map.get(orbited).unwrap();
+// Usage: day06 [FROM TO] [--path] [--dot FILE [--highlight] [--collapse]]
+fn main() {
+    let mut endpoints = vec![];
+    let mut show_path = false;
+    let mut dot = None;
+    let (mut highlight, mut collapse) = (false, false);
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--path" => show_path = true,
+            "--dot" if dot.is_none() => {
+                dot = Some(args.next().unwrap_or_else(|| {
+                    eprintln!("{}", USAGE);
+                    process::exit(1);
+                }))
+            }
+            "--highlight" => highlight = true,
+            "--collapse" => collapse = true,
+            _ if !arg.starts_with("--") && endpoints.len() < 2 => endpoints.push(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(1);
+            }
         }
     }
-    count
-}
-
-fn solve_p2(edges: &[(&str, &str)]) -> usize {
-    let mut orbit_map: HashMap<&str, &str> = HashMap::new();
-    for (orbited, orbiter) in edges {
-        orbit_map.insert(orbiter, orbited);
+    let (from, to) = match endpoints.as_slice() {
+        [] => ("YOU", "SAN"),
+        [from, to] => (from.as_str(), to.as_str()),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+    if dot.is_none() && (highlight || collapse) {
+        eprintln!("{}", USAGE);
+        process::exit(1);
     }
 
-    let mut hops = 0;
-    let mut route: HashMap<&str, usize> = HashMap::new();
-    let mut orbited = orbit_map.get("YOU").unwrap();
-    while orbit_map.contains_key(orbited) {
-        hops += 1;
-        orbited = orbit_map.get(orbited).unwrap();
-        route.insert(orbited, hops);
+    let input = fs::read_to_string("./input/day06.in").unwrap();
+    let tree = orbits::validate(&input, "COM", &[from, to]).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    if let Some(path) = dot {
+        let options = DotOptions {
+            highlight: Some((from, to)).filter(|_| highlight),
+            collapse,
+        };
+        let file = File::create(&path).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        });
+        orbits::write_dot(&tree, &options, &mut BufWriter::new(file)).unwrap();
     }
 
-    hops = 0;
-    orbited = orbit_map.get("SAN").unwrap();
-    while orbit_map.contains_key(orbited) {
-        hops += 1;
-        orbited = orbit_map.get(orbited).unwrap();
-        if route.contains_key(orbited) {
-            return hops + route.get(orbited).unwrap();
+    println!("p1: {}", tree.total_orbits());
+    match tree.transfers(from, to) {
+        Some(transfers) => println!("p2: {}", transfers),
+        None => println!("p2: no transfer from {} to {}", from, to),
+    }
+    if show_path {
+        match tree.path(from, to) {
+            Some(path) => println!("path: {}", path.join(" -> ")),
+            None => println!("path: {} and {} are not connected", from, to),
         }
     }
-    panic!("no route found");
-}
-
-fn main() {
-    let input = fs::read_to_string("./input/day06.in").unwrap();
-    let edges: Vec<(&str, &str)> = input
-        .split('\n')
-        .filter(|ele| !ele.is_empty())
-        .map(|ele| {
-            let edge: Vec<&str> = ele.split(')').collect();
-            (edge[0], edge[1])
-        })
-        .collect();
-
-    println!("p1: {}", solve_p1(&edges));
-    println!("p2: {}", solve_p2(&edges));
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use aoc2019::orbits::OrbitTree;
 
     #[test]
     fn test_solve_p1() {
         assert_eq!(
-            solve_p1(&[
+            OrbitTree::new(&[
                 ("COM", "B"),
                 ("B", "C"),
                 ("C", "D"),
@@ -79,7 +94,8 @@ mod tests {
                 ("E", "J"),
                 ("J", "K"),
                 ("K", "L"),
-            ]),
+            ])
+            .total_orbits(),
             42
         );
     }
@@ -87,7 +103,7 @@ mod tests {
     #[test]
     fn test_solve_p2() {
         assert_eq!(
-            solve_p2(&[
+            OrbitTree::new(&[
                 ("COM", "B"),
                 ("B", "C"),
                 ("C", "D"),
@@ -101,8 +117,9 @@ mod tests {
                 ("K", "L"),
                 ("K", "YOU"),
                 ("I", "SAN"),
-            ]),
-            4
+            ])
+            .transfers("YOU", "SAN"),
+            Some(4)
         );
     }
 }
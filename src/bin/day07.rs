@@ -1,85 +1,194 @@
+use aoc2019::intcode::amplifiers::{Best, Circuit, Stage, Wiring};
 use aoc2019::intcode::IntCodeCpu;
 use itertools::Itertools;
+use std::env;
 use std::fs;
+use std::ops::Range;
+use std::process;
+use std::thread;
+use std::time::Instant;
 
-fn run_amplifiers(cpu: &IntCodeCpu) -> i64 {
-    (0..5)
-        .permutations(5)
-        .map(|phase_settings| {
-            let mut output = 0;
-            for phase_setting in phase_settings {
-                let mut amplifier = cpu.clone();
-                amplifier.input.push_back(phase_setting);
-                amplifier.input.push_back(output);
-                amplifier.run();
-                output = amplifier.output.pop_front().unwrap();
-            }
-            output
-        })
-        .max()
-        .unwrap()
+// Phase settings the day 7 program understands, others make it jump out of its code
+const PROGRAM_PHASES: Range<i64> = 0..10;
+// Phase settings that put the program into feedback mode
+const FEEDBACK_PHASES: Range<i64> = 5..10;
+
+const USAGE: &str = "usage: day07 [--circuit SPEC] [--phases LO..HI|LIST] [--feedback] \
+                     [--threads N] [--bench ROUNDS]";
+
+fn amplifiers() -> Circuit {
+    Circuit::new(Stage::chain(5), (0..5).collect(), Wiring::Series)
 }
 
-fn run_amplifiers_with_feedback(cpu: &IntCodeCpu) -> i64 {
-    (5..10)
-        .permutations(5)
-        .map(|phase_settings| {
-            let mut amplifiers: Vec<IntCodeCpu> = phase_settings
-                .iter()
-                .map(|phase_setting| {
-                    let mut amplifier = cpu.clone();
-                    amplifier.input.push_back(*phase_setting);
-                    amplifier.set_running();
-                    amplifier
-                })
-                .collect();
-
-            let mut output = 0;
-            while amplifiers.first().unwrap().running() {
-                for amplifier in &mut amplifiers {
-                    amplifier.input.push_back(output);
-                    if let Some(out) = amplifier.run_until_output() {
-                        output = out;
-                    } else {
-                        break;
-                    }
-                }
-            }
-            output
-        })
-        .max()
-        .unwrap()
+fn amplifiers_with_feedback() -> Circuit {
+    Circuit::new(Stage::chain(5), FEEDBACK_PHASES.collect(), Wiring::Feedback)
+}
+
+// Parses a range like 5..10 or a list like 0,2,4
+fn parse_phases(s: &str) -> Option<Vec<i64>> {
+    if let Some((lo, hi)) = s.split_once("..") {
+        return Some((lo.parse().ok()?..hi.parse().ok()?).collect());
+    }
+    s.split(',').map(|p| p.trim().parse().ok()).collect()
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn print_best(label: &str, best: &Best) {
+    println!(
+        "{}: {} (phases {})",
+        label,
+        best.signal,
+        best.phases.iter().join(",")
+    );
+}
+
+// Times the sequential and the threaded search over the given number of rounds
+fn bench(label: &str, circuit: &Circuit, cpu: &IntCodeCpu, threads: usize, rounds: u32) {
+    let start = Instant::now();
+    let mut sequential = None;
+    for _ in 0..rounds {
+        sequential = circuit.search(cpu);
+    }
+    let sequential_time = start.elapsed() / rounds;
+
+    let start = Instant::now();
+    let mut parallel = None;
+    for _ in 0..rounds {
+        parallel = circuit.search_parallel(cpu, threads);
+    }
+    let parallel_time = start.elapsed() / rounds;
+
+    assert_eq!(sequential, parallel);
+    println!(
+        "{}: {} assignments, sequential {:.2?}, {} threads {:.2?} ({:.1}x)",
+        label,
+        circuit.assignments().count(),
+        sequential_time,
+        threads,
+        parallel_time,
+        sequential_time.as_secs_f64() / parallel_time.as_secs_f64()
+    );
 }
 
+// Usage: day07 [--circuit SPEC] [--phases LO..HI|LIST] [--feedback] [--threads N] [--bench ROUNDS]
 fn main() {
+    let mut stages = None;
+    let mut phases = None;
+    let mut wiring = Wiring::Series;
+    let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut rounds = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--circuit" => {
+                let spec = args.next().unwrap_or_else(|| usage());
+                stages = Some(spec.parse::<Stage>().unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }));
+            }
+            "--phases" => {
+                let list = args.next().unwrap_or_else(|| usage());
+                phases = Some(parse_phases(&list).unwrap_or_else(|| usage()));
+            }
+            "--feedback" => wiring = Wiring::Feedback,
+            "--threads" => {
+                threads = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .filter(|n| *n > 0)
+                    .unwrap_or_else(|| usage());
+            }
+            "--bench" => {
+                rounds = Some(
+                    args.next()
+                        .and_then(|n| n.parse::<u32>().ok())
+                        .filter(|n| *n > 0)
+                        .unwrap_or_else(|| usage()),
+                );
+            }
+            _ => usage(),
+        }
+    }
+
     let input = fs::read_to_string("./input/day07.in").unwrap();
     let cpu = IntCodeCpu::from_code(&input);
 
-    println!("p1: {}", run_amplifiers(&cpu));
-    println!("p2: {}", run_amplifiers_with_feedback(&cpu));
+    let circuits = if stages.is_none() && phases.is_none() && wiring == Wiring::Series {
+        vec![("p1", amplifiers()), ("p2", amplifiers_with_feedback())]
+    } else {
+        let stages = stages.unwrap_or_else(|| Stage::chain(5));
+        let phases = phases.unwrap_or_else(|| match wiring {
+            Wiring::Series => (0..stages.amps() as i64).collect(),
+            Wiring::Feedback => FEEDBACK_PHASES.collect(),
+        });
+        if wiring == Wiring::Feedback && stages.amps() > FEEDBACK_PHASES.count() {
+            eprintln!("feedback wiring only has the phase settings 5..=9, so at most 5 amplifiers");
+            process::exit(1);
+        }
+        if let Some(phase) = phases.iter().find(|p| !PROGRAM_PHASES.contains(p)) {
+            eprintln!("phase setting {} is not in 0..=9", phase);
+            process::exit(1);
+        }
+        vec![("best", Circuit::new(stages, phases, wiring))]
+    };
+
+    for (label, circuit) in &circuits {
+        if let Some(rounds) = rounds {
+            bench(label, circuit, &cpu, threads, rounds);
+            continue;
+        }
+        match circuit.search_parallel(&cpu, threads) {
+            Some(best) => print_best(label, &best),
+            None => {
+                eprintln!("no phase assignment produces a signal");
+                process::exit(1);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn run_amplifiers(cpu: &IntCodeCpu) -> Best {
+        amplifiers().search_parallel(cpu, 4).unwrap()
+    }
+
+    fn run_amplifiers_with_feedback(cpu: &IntCodeCpu) -> Best {
+        amplifiers_with_feedback().search_parallel(cpu, 4).unwrap()
+    }
+
     #[test]
     fn test_run_amplifiers() {
         assert_eq!(
             run_amplifiers(&IntCodeCpu::from_code(
                 "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0"
-            )),
+            ))
+            .signal,
             43_210
         );
     }
 
+    #[test]
+    fn test_best_phases() {
+        let cpu = IntCodeCpu::from_code("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
+        assert_eq!(run_amplifiers(&cpu).phases, vec![4, 3, 2, 1, 0]);
+    }
+
     #[test]
     fn test_run_amplifiers2() {
         assert_eq!(
             run_amplifiers(&IntCodeCpu::from_code(
                 "3,23,3,24,1002,24,10,24,1002,23,-1,23,
                 101,5,23,23,1,24,23,23,4,23,99,0,0"
-            )),
+            ))
+            .signal,
             54_321
         );
     }
@@ -90,7 +199,8 @@ mod tests {
             run_amplifiers(&IntCodeCpu::from_code(
                 "3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,
                 1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0"
-            )),
+            ))
+            .signal,
             65_210
         );
     }
@@ -101,7 +211,8 @@ mod tests {
             run_amplifiers_with_feedback(&IntCodeCpu::from_code(
                 "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,
                 27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5"
-            )),
+            ))
+            .signal,
             139_629_729
         );
     }
@@ -113,7 +224,8 @@ mod tests {
                 "3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,
                 -5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,
                 53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10"
-            )),
+            ))
+            .signal,
             18_216
         );
     }
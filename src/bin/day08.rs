@@ -1,57 +1,15 @@
+use aoc2019::ocr;
+use aoc2019::sif::{Pixel, SifImage};
+use std::env;
 use std::fs;
+use std::process;
 
-const WIDTH: usize = 25;
-const HEIGHT: usize = 6;
-const LAYER_SIZE: usize = WIDTH * HEIGHT;
-
-fn get_layers(data: &[u8], lsize: usize) -> Vec<Vec<u8>> {
-    data.chunks(lsize).map(|ch| ch.to_vec()).collect()
-}
-
-fn verify_data(data: &[u8], lsize: usize) -> u32 {
-    let layers = get_layers(&data, lsize);
-
-    let (_, ones, twos) = layers
-        .iter()
-        .map(|e| {
-            let mut digits_count: (u32, u32, u32) = (0, 0, 0);
-            for d in e.iter() {
-                match d {
-                    0 => digits_count.0 += 1,
-                    1 => digits_count.1 += 1,
-                    2 => digits_count.2 += 1,
-                    _ => panic!("unknown color code: {}", d),
-                }
-            }
-            digits_count
-        })
-        .min_by_key(|(zeros, _, _)| *zeros)
-        .unwrap();
-
-    ones * twos
-}
-
-fn decode_ssif(data: &[u8], lsize: usize) -> Vec<u8> {
-    let layers = get_layers(&data, lsize);
-
-    let mut img_data = vec![2u8; lsize];
-
-    for layer in layers {
-        for (ip, lp) in img_data.iter_mut().zip(layer.iter()) {
-            if *ip == 2 && *lp != 2 {
-                *ip = *lp;
-            }
-        }
-    }
-    img_data
-}
-
-fn print_image(img_data: &[u8], width: usize) {
-    img_data.chunks(width).for_each(|l| {
+fn print_image(pixels: &[Pixel], width: usize) {
+    pixels.chunks(width).for_each(|l| {
         let line: String = l
             .iter()
             .map(|p| match p {
-                1 => '█',
+                Pixel::White => '█',
                 _ => ' ',
             })
             .collect();
@@ -59,19 +17,80 @@ fn print_image(img_data: &[u8], width: usize) {
     })
 }
 
+// Parses dimensions like 25x6
+fn parse_size(s: &str) -> Option<(usize, usize)> {
+    let (width, height) = s.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+fn usage() -> ! {
+    eprintln!("usage: day08 [--size WxH] [--image FILE [--scale N]]");
+    process::exit(1);
+}
+
+// Usage: day08 [--size WxH] [--image FILE [--scale N]]
 fn main() {
+    let (mut width, mut height) = (25, 6);
+    let mut image_path = None;
+    let mut scale = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" => {
+                let size = args.next().unwrap_or_else(|| usage());
+                (width, height) = parse_size(&size).unwrap_or_else(|| {
+                    eprintln!("invalid size: {}", size);
+                    process::exit(1);
+                });
+            }
+            "--image" => image_path = Some(args.next().unwrap_or_else(|| usage())),
+            "--scale" => {
+                scale = args
+                    .next()
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .filter(|n| *n > 0);
+                if scale.is_none() {
+                    usage();
+                }
+            }
+            _ => usage(),
+        }
+    }
+    if image_path.is_none() && scale.is_some() {
+        usage();
+    }
+
     let input = fs::read_to_string("./input/day08.in").unwrap();
+    let image = SifImage::decode(&input, width, height).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
 
-    let digits: Vec<u8> = input
-        .trim()
-        .chars()
-        .map(|c| c.to_digit(10).unwrap() as u8)
-        .collect();
+    if let Some(path) = image_path {
+        image
+            .to_bitmap()
+            .save(&path, scale.unwrap_or(10))
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+    }
 
-    println!("p1: {}", verify_data(&digits, LAYER_SIZE));
+    println!("p1: {}", image.checksum());
 
-    println!("p2: ");
-    print_image(&decode_ssif(&digits, LAYER_SIZE), WIDTH);
+    let pixels = image.render();
+    let grid: Vec<Vec<bool>> = pixels
+        .chunks(image.width())
+        .map(|row| row.iter().map(|p| *p == Pixel::White).collect())
+        .collect();
+    match ocr::recognize(&grid) {
+        Ok(text) => println!("p2: {}", text),
+        Err(e) => {
+            eprintln!("{}", e);
+            println!("p2: ");
+            print_image(&pixels, image.width());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -81,8 +100,8 @@ mod tests {
     #[test]
     fn test_decode_ssif() {
         assert_eq!(
-            decode_ssif(&[0, 2, 2, 2, 1, 1, 2, 2, 2, 2, 1, 2, 0, 0, 0, 0], 4),
-            &[0, 1, 1, 0]
+            SifImage::decode("0222112222120000", 2, 2).unwrap().render(),
+            &[Pixel::Black, Pixel::White, Pixel::White, Pixel::Black]
         );
     }
 }
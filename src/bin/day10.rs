@@ -1,7 +1,11 @@
 use itertools::Itertools;
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs;
 use std::io;
+use std::process;
+use std::time::Instant;
 
 struct Asteroid {
     position: (usize, usize),
@@ -34,34 +38,57 @@ fn get_direction((to_x, to_y): (usize, usize), (from_x, from_y): (usize, usize))
     }
 }
 
-fn get_directions(asteroids: &[(usize, usize)], from: (usize, usize)) -> Vec<(i32, i32)> {
-    asteroids.iter().map(|&a| get_direction(a, from)).collect()
+// Clockwise position of a direction starting from up, with y growing downwards: quadrants
+// from up, right, down and left, each including its starting axis
+fn quadrant((x, y): (i32, i32)) -> u8 {
+    match (x, y) {
+        (x, y) if x >= 0 && y < 0 => 0,
+        (x, y) if x > 0 && y >= 0 => 1,
+        (x, y) if x <= 0 && y > 0 => 2,
+        _ => 3,
+    }
+}
+
+// Orders directions clockwise from up without floating point: by quadrant, then by the sign of
+// the cross product, which is positive if b is clockwise of a within less than half a turn
+fn clockwise(a: (i32, i32), b: (i32, i32)) -> Ordering {
+    let cross = a.0 as i64 * b.1 as i64 - a.1 as i64 * b.0 as i64;
+    quadrant(a).cmp(&quadrant(b)).then(0.cmp(&cross))
 }
 
-fn is_in_sight(
+// All other asteroids in the order the laser at the station vaporizes them. The laser starts
+// pointing up, rotates clockwise and hits the closest remaining asteroid in every direction,
+// as many rotations as needed.
+fn vaporization_order(
     asteroids: &[(usize, usize)],
-    (to_x, to_y): (usize, usize),
-    (from_x, from_y): (usize, usize),
-) -> bool {
-    if !asteroids.contains(&(to_x, to_y)) {
-        return false;
-    }
-    if from_x == to_x && from_y == to_y {
-        return false;
+    station: (usize, usize),
+) -> Vec<(usize, usize)> {
+    let distance = |(x, y): (usize, usize)| {
+        (x as i64 - station.0 as i64).abs() + (y as i64 - station.1 as i64).abs()
+    };
+    let mut by_direction: Vec<_> = asteroids
+        .iter()
+        .filter(|&&a| a != station)
+        .map(|&a| (get_direction(a, station), a))
+        .into_group_map()
+        .into_iter()
+        .collect();
+    by_direction.sort_by(|a, b| clockwise(a.0, b.0));
+    for (_, line) in &mut by_direction {
+        // farthest first, so the next target can be popped
+        line.sort_by_key(|a| std::cmp::Reverse(distance(*a)));
     }
 
-    let (mut tp_x, mut tp_y) = (from_x, from_y);
-
-    while (tp_x, tp_y) != (to_x, to_y) {
-        let (xdir, ydir) = get_direction((to_x, to_y), (from_x, from_y));
-
-        tp_x = (tp_x as i32 + xdir) as usize;
-        tp_y = (tp_y as i32 + ydir) as usize;
-        if asteroids.contains(&(tp_x, tp_y)) && (tp_x, tp_y) != (to_x, to_y) {
-            return false;
+    let targets = by_direction.iter().map(|(_, line)| line.len()).sum();
+    let mut order = Vec::with_capacity(targets);
+    while order.len() < targets {
+        for (_, line) in &mut by_direction {
+            if let Some(target) = line.pop() {
+                order.push(target);
+            }
         }
     }
-    true
+    order
 }
 
 fn find_best_asteroid(map: &HashMap<(usize, usize), usize>) -> Asteroid {
@@ -79,21 +106,53 @@ fn find_best_asteroid(map: &HashMap<(usize, usize), usize>) -> Asteroid {
     best
 }
 
+// Asteroids are visible from a station if no other asteroid lies in the same reduced
+// direction closer to it, so every direction contributes exactly one visible asteroid
 fn get_visible_counts_per_pos(asteroids: &[(usize, usize)]) -> HashMap<(usize, usize), usize> {
-    let mut vis_counts: HashMap<(usize, usize), usize> = HashMap::new();
-
-    for from in asteroids {
-        for to in asteroids {
-            if is_in_sight(&asteroids, *to, *from) {
-                if let Some(x) = vis_counts.get_mut(from) {
-                    *x += 1;
-                } else {
-                    vis_counts.insert(*from, 1);
-                }
+    asteroids
+        .iter()
+        .map(|&from| {
+            let directions: HashSet<(i32, i32)> = asteroids
+                .iter()
+                .filter(|&&to| to != from)
+                .map(|&to| get_direction(to, from))
+                .collect();
+            (from, directions.len())
+        })
+        .collect()
+}
+
+// A size x size field where about a third of the positions hold an asteroid, from a fixed
+// xorshift sequence so every run measures the same map
+fn generate_field(size: usize) -> Vec<(usize, usize)> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut asteroids = vec![];
+    for y in 0..size {
+        for x in 0..size {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            if state % 3 == 0 {
+                asteroids.push((x, y));
             }
         }
     }
-    vis_counts
+    asteroids
+}
+
+fn bench(size: usize) {
+    let asteroids = generate_field(size);
+    let start = Instant::now();
+    let best = find_best_asteroid(&get_visible_counts_per_pos(&asteroids));
+    println!(
+        "{}x{} map with {} asteroids: best position {:?} sees {}, took {:.2?}",
+        size,
+        size,
+        asteroids.len(),
+        best.position,
+        best.in_sight_count,
+        start.elapsed()
+    );
 }
 
 fn parse_raw_map(raw_map: &str) -> Vec<(usize, usize)> {
@@ -108,7 +167,43 @@ fn parse_raw_map(raw_map: &str) -> Vec<(usize, usize)> {
     asteroids
 }
 
+// 1st, 2nd, 3rd, 4th, ..., 11th, 12th, 13th, ..., 21st
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+enum Query {
+    Nth(usize),
+    All,
+}
+
+const USAGE: &str = "usage: day10 [--nth N | --all] | day10 --map-size N";
+
+// Usage: day10 [--nth N | --all] | day10 --map-size N
 fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let parse = |n: &str| n.parse::<usize>().ok().filter(|n| *n > 0);
+    let query = match args.as_slice() {
+        [] => Query::Nth(200),
+        [flag] if flag == "--all" => Query::All,
+        [flag, n] if flag == "--nth" && parse(n).is_some() => Query::Nth(parse(n).unwrap()),
+        [flag, size] if flag == "--map-size" && parse(size).is_some() => {
+            bench(parse(size).unwrap());
+            return Ok(());
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
     let raw_map = fs::read_to_string("./input/day10.in")?;
     let asteroids = parse_raw_map(&raw_map);
 
@@ -119,34 +214,20 @@ fn main() -> io::Result<()> {
         best_asteroid.position, best_asteroid.in_sight_count
     );
 
-    let laser_directions: Vec<(i32, i32)> = get_directions(&asteroids, best_asteroid.position)
-        .into_iter()
-        .filter(|e| *e != (0, 0))
-        .unique()
-        .sorted_by(|a, b| {
-            (f64::from(a.0).atan2(f64::from(a.1)))
-                .partial_cmp(&f64::from(b.0).atan2(f64::from(b.1)))
-                .unwrap()
-                .reverse()
-        })
-        .collect();
-    let mut asteroids_by_direction = asteroids
-        .into_iter()
-        .map(|e| (get_direction(e, best_asteroid.position), e))
-        .into_group_map();
-
-    let mut vapor_count = 0;
-    for dir in &laser_directions {
-        if let Some(asteroids) = asteroids_by_direction.get_mut(dir) {
-            if !asteroids.is_empty() {
-                let target = asteroids.remove(0);
-                vapor_count += 1;
-                if vapor_count == 200 {
-                    println!(
-                        "p2: 200th asteroid to be vaporized is at position ({}, {})",
-                        target.0, target.1
-                    );
-                }
+    let order = vaporization_order(&asteroids, best_asteroid.position);
+    match query {
+        Query::Nth(n) => match order.get(n - 1) {
+            Some((x, y)) => println!(
+                "p2: {} asteroid to be vaporized is at position ({}, {})",
+                ordinal(n),
+                x,
+                y
+            ),
+            None => println!("p2: only {} asteroids can be vaporized", order.len()),
+        },
+        Query::All => {
+            for (i, (x, y)) in order.iter().enumerate() {
+                println!("{}: ({}, {})", i + 1, x, y);
             }
         }
     }
@@ -157,6 +238,66 @@ fn main() -> io::Result<()> {
 mod tests {
     use super::*;
 
+    const LARGE_EXAMPLE: &str = ".#..##.###...#######
+        ##.############..##.
+        .#.######.########.#
+        .###.#######.####.#.
+        #####.##.#.##.###.##
+        ..#####..#.#########
+        ####################
+        #.####....###.#.#.##
+        ##.#################
+        #####.##.###..####..
+        ..######..##.#######
+        ####.##.####...##..#
+        .#####..#.######.###
+        ##...#.##########...
+        #.##########.#######
+        .####.#.###.###.#.##
+        ....##.##.###..#####
+        .#.#.###########.###
+        #.#.#.#####.####.###
+        ###.##.####.##.#..##";
+
+    fn is_in_sight(
+        asteroids: &[(usize, usize)],
+        (to_x, to_y): (usize, usize),
+        (from_x, from_y): (usize, usize),
+    ) -> bool {
+        if !asteroids.contains(&(to_x, to_y)) {
+            return false;
+        }
+        if from_x == to_x && from_y == to_y {
+            return false;
+        }
+
+        let (mut tp_x, mut tp_y) = (from_x, from_y);
+
+        while (tp_x, tp_y) != (to_x, to_y) {
+            let (xdir, ydir) = get_direction((to_x, to_y), (from_x, from_y));
+
+            tp_x = (tp_x as i32 + xdir) as usize;
+            tp_y = (tp_y as i32 + ydir) as usize;
+            if asteroids.contains(&(tp_x, tp_y)) && (tp_x, tp_y) != (to_x, to_y) {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Counts by walking the ray to every other asteroid
+    fn visible_counts_by_rays(asteroids: &[(usize, usize)]) -> HashMap<(usize, usize), usize> {
+        let mut vis_counts = HashMap::new();
+        for from in asteroids {
+            for to in asteroids {
+                if is_in_sight(asteroids, *to, *from) {
+                    *vis_counts.entry(*from).or_insert(0) += 1;
+                }
+            }
+        }
+        vis_counts
+    }
+
     #[test]
     fn test_is_in_sight() {
         let asteroids = parse_raw_map(
@@ -167,9 +308,128 @@ mod tests {
         ...##",
         );
 
-        assert_eq!(is_in_sight(&asteroids, (4, 0), (1, 0)), true);
-        assert_eq!(is_in_sight(&asteroids, (2, 0), (1, 0)), false);
-        assert_eq!(is_in_sight(&asteroids, (1, 2), (1, 0)), true);
-        assert_eq!(is_in_sight(&asteroids, (1, 0), (3, 4)), false);
+        assert!(is_in_sight(&asteroids, (4, 0), (1, 0)));
+        assert!(!is_in_sight(&asteroids, (2, 0), (1, 0)));
+        assert!(is_in_sight(&asteroids, (1, 2), (1, 0)));
+        assert!(!is_in_sight(&asteroids, (1, 0), (3, 4)));
+    }
+
+    #[test]
+    fn test_visible_counts() {
+        let small = ".#..#
+        .....
+        #####
+        ....#
+        ...##";
+        let medium = "......#.#.
+        #..#.#....
+        ..#######.
+        .#.#.###..
+        .#..#.....
+        ..#....#.#
+        #..#....#.
+        .##.#..###
+        ##...#..#.
+        .#....####";
+        for (map, position, count) in [(small, (3, 4), 8), (medium, (5, 8), 33)] {
+            let asteroids = parse_raw_map(map);
+            let counts = get_visible_counts_per_pos(&asteroids);
+            assert_eq!(counts, visible_counts_by_rays(&asteroids));
+            let best = find_best_asteroid(&counts);
+            assert_eq!((best.position, best.in_sight_count), (position, count));
+        }
+
+        let best = find_best_asteroid(&get_visible_counts_per_pos(&parse_raw_map(LARGE_EXAMPLE)));
+        assert_eq!((best.position, best.in_sight_count), ((11, 13), 210));
+    }
+
+    #[test]
+    fn test_clockwise() {
+        let compass = [
+            (0, -1),
+            (1, -2),
+            (1, -1),
+            (1, 0),
+            (2, 1),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+            (-2, -1),
+            (-1, -1),
+            (-1, -2),
+        ];
+        for (i, a) in compass.iter().enumerate() {
+            for (j, b) in compass.iter().enumerate() {
+                assert_eq!(clockwise(*a, *b), i.cmp(&j), "{:?} {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_vaporization_order() {
+        let asteroids = parse_raw_map(LARGE_EXAMPLE);
+        let order = vaporization_order(&asteroids, (11, 13));
+        assert_eq!(order.len(), asteroids.len() - 1);
+        for (n, position) in [
+            (1, (11, 12)),
+            (2, (12, 1)),
+            (3, (12, 2)),
+            (10, (12, 8)),
+            (20, (16, 0)),
+            (50, (16, 9)),
+            (100, (10, 16)),
+            (199, (9, 6)),
+            (200, (8, 2)),
+            (201, (10, 9)),
+            (299, (11, 1)),
+        ] {
+            assert_eq!(order[n - 1], position, "{}", n);
+        }
+
+        let asteroids = parse_raw_map(
+            ".#....#####...#..
+            ##...##.#####..##
+            ##...#...#.#####.
+            ..#.....X...###..
+            ..#.#.....#....##",
+        );
+        assert_eq!(
+            vaporization_order(&asteroids, (8, 3))[..9],
+            [
+                (8, 1),
+                (9, 0),
+                (9, 1),
+                (10, 0),
+                (9, 2),
+                (11, 1),
+                (12, 1),
+                (11, 2),
+                (15, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_several_rotations() {
+        // a column above the station and one asteroid to the right take three rotations
+        let asteroids = parse_raw_map(
+            "#.
+            #.
+            #.
+            ##",
+        );
+        assert_eq!(
+            vaporization_order(&asteroids, (0, 3)),
+            [(0, 2), (1, 3), (0, 1), (0, 0)]
+        );
+    }
+
+    #[test]
+    fn test_generate_field() {
+        let field = generate_field(30);
+        assert_eq!(field, generate_field(30));
+        assert!(field.len() > 200 && field.len() < 400);
+        assert!(field.iter().all(|&(x, y)| x < 30 && y < 30));
     }
 }
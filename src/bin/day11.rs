@@ -1,7 +1,11 @@
+use aoc2019::bitmap::{self, Bitmap};
 use aoc2019::intcode::IntCodeCpu;
+use aoc2019::ocr;
 use std::collections::HashMap;
+use std::env;
 use std::fs;
 use std::io;
+use std::process;
 
 #[derive(PartialEq, Clone, Copy, Debug)]
 enum Color {
@@ -104,11 +108,13 @@ fn show_panel(visited_positions: &HashMap<(i32, i32), Color>) {
     let ymin = visited_positions.keys().min_by_key(|ys| ys.1).unwrap().1;
     let ymax = visited_positions.keys().max_by_key(|ys| ys.1).unwrap().1;
 
-    let mut panel =
-        vec![vec![' '; (xmax - xmin).abs() as usize + 1]; (ymax - ymin).abs() as usize + 1];
+    let mut panel = vec![
+        vec![' '; (xmax - xmin).unsigned_abs() as usize + 1];
+        (ymax - ymin).unsigned_abs() as usize + 1
+    ];
 
     for pos in visited_positions.keys() {
-        if *visited_positions.get(&pos).unwrap() == Color::White {
+        if *visited_positions.get(pos).unwrap() == Color::White {
             let (xs, ys) = pos;
             panel[(*ys + ymin.abs()) as usize][(*xs + xmin.abs()) as usize] = '█';
         }
@@ -122,9 +128,54 @@ fn show_panel(visited_positions: &HashMap<(i32, i32), Color>) {
     }
 }
 
+// The painted area row by row, true for white panels. Panels that were never painted are black.
+fn hull_grid(visited_positions: &HashMap<(i32, i32), Color>) -> Vec<Vec<bool>> {
+    let xmin = visited_positions.keys().map(|p| p.0).min().unwrap();
+    let xmax = visited_positions.keys().map(|p| p.0).max().unwrap();
+    let ymin = visited_positions.keys().map(|p| p.1).min().unwrap();
+    let ymax = visited_positions.keys().map(|p| p.1).max().unwrap();
+
+    (ymin..=ymax)
+        .map(|y| {
+            (xmin..=xmax)
+                .map(|x| visited_positions.get(&(x, y)) == Some(&Color::White))
+                .collect()
+        })
+        .collect()
+}
+
+fn hull_bitmap(grid: &[Vec<bool>]) -> Bitmap {
+    Bitmap::from_fn(grid[0].len(), grid.len(), |x, y| {
+        if grid[y][x] {
+            bitmap::WHITE
+        } else {
+            bitmap::BLACK
+        }
+    })
+}
+
 const BASIC_PANEL_COLOR: Color = Color::White;
 
+fn usage() -> ! {
+    eprintln!("usage: day11 [--image FILE [--scale N]]");
+    process::exit(1);
+}
+
+// Usage: day11 [--image FILE [--scale N]]
 fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (image_path, scale) = match args.as_slice() {
+        [] => (None, 10),
+        [flag, path] if flag == "--image" => (Some(path), 10),
+        [flag, path, scale_flag, scale] if flag == "--image" && scale_flag == "--scale" => {
+            match scale.parse::<usize>() {
+                Ok(scale) if scale > 0 => (Some(path), scale),
+                _ => usage(),
+            }
+        }
+        _ => usage(),
+    };
+
     let code = fs::read_to_string("./input/day11.in")?;
 
     let mut robot = Robot::new(IntCodeCpu::from_code(&code));
@@ -151,10 +202,21 @@ fn main() -> io::Result<()> {
         }
     }
 
-    println!("p1: {}", robot.visited_positions.iter().count());
+    println!("p1: {}", robot.visited_positions.len());
 
-    println!("p2: ");
-    show_panel(&robot.visited_positions);
+    let grid = hull_grid(&robot.visited_positions);
+    match ocr::recognize(&grid) {
+        Ok(text) => println!("p2: {}", text),
+        Err(e) => {
+            eprintln!("{}", e);
+            println!("p2: ");
+            show_panel(&robot.visited_positions);
+        }
+    }
+
+    if let Some(path) = image_path {
+        hull_bitmap(&grid).save(path, scale)?;
+    }
 
     Ok(())
 }
@@ -84,7 +84,7 @@ fn parse_input(input: &str) -> Vec<Moon> {
         .map(|pos| {
             let mut moon = Moon::new((0, 0, 0));
             for (i, p) in pos[1..pos.len() - 1].split(',').enumerate() {
-                let val = p.split('=').last().unwrap().parse::<i32>().unwrap();
+                let val = p.split('=').next_back().unwrap().parse::<i32>().unwrap();
                 match i {
                     0 => moon.position.0 = val,
                     1 => moon.position.1 = val,
@@ -97,8 +97,8 @@ fn parse_input(input: &str) -> Vec<Moon> {
         .collect()
 }
 
-fn time_step(moons: &mut Vec<Moon>) {
-    let moons_cloned = moons.clone();
+fn time_step(moons: &mut [Moon]) {
+    let moons_cloned = moons.to_vec();
     for moon in moons.iter_mut() {
         for other in moons_cloned.iter() {
             if moon.position != other.position {
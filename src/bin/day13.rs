@@ -1,7 +1,9 @@
+use aoc2019::intcode::replay::{Player, Recorder, Replay, ReplayError};
 use aoc2019::intcode::{Event, IntCodeCpu};
 use console::{style, Term};
 use itertools::Itertools;
 use std::cmp::Ordering;
+use std::env;
 use std::fs;
 use std::io;
 
@@ -28,12 +30,45 @@ impl From<i64> for TileID {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 struct Tile {
     position: (i64, i64),
     id: TileID,
 }
 
-fn play(cpu: &mut IntCodeCpu) -> io::Result<i64> {
+// Where the joystick input comes from
+enum Joystick {
+    // Follow the ball with the paddle, optionally recording the moves
+    Follow(Option<Recorder>),
+    Replay(Player),
+}
+
+impl Joystick {
+    fn supply(&mut self, cpu: &mut IntCodeCpu, ball: &Tile, paddle: &Tile) -> io::Result<()> {
+        match self {
+            Joystick::Follow(recorder) => {
+                let val = match ball.position.0.cmp(&paddle.position.0) {
+                    Ordering::Less => -1,
+                    Ordering::Equal => 0,
+                    Ordering::Greater => 1,
+                };
+                match recorder {
+                    Some(recorder) => recorder.supply(cpu, val),
+                    None => cpu.input.push_back(val),
+                }
+                Ok(())
+            }
+            Joystick::Replay(player) => player.supply(cpu).map_err(invalid_data),
+        }
+    }
+}
+
+fn invalid_data(e: ReplayError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+// Plays the game, drawing it to the terminal if one is given
+fn play(cpu: &mut IntCodeCpu, term: Option<&Term>, joystick: &mut Joystick) -> io::Result<i64> {
     let mut score = 0;
 
     cpu.poke_memory(0, 2);
@@ -48,9 +83,10 @@ fn play(cpu: &mut IntCodeCpu) -> io::Result<i64> {
         id: TileID::HPaddle,
     };
 
-    let term = Term::stdout();
-    term.hide_cursor()?;
-    term.clear_screen()?;
+    if let Some(term) = term {
+        term.hide_cursor()?;
+        term.clear_screen()?;
+    }
 
     let mut outputs = vec![];
     loop {
@@ -65,8 +101,10 @@ fn play(cpu: &mut IntCodeCpu) -> io::Result<i64> {
                     let val = outputs[2];
                     if x == -1 && y == 0 {
                         score = val;
-                        term.move_cursor_to(0, 0)?;
-                        term.write_str(&format!("score: {}", score))?;
+                        if let Some(term) = term {
+                            term.move_cursor_to(0, 0)?;
+                            term.write_str(&format!("score: {}", score))?;
+                        }
                     } else {
                         let c = match TileID::from(val) {
                             TileID::Empty => style(' '),
@@ -81,25 +119,43 @@ fn play(cpu: &mut IntCodeCpu) -> io::Result<i64> {
                                 style('●').red()
                             }
                         };
-                        term.move_cursor_to(x as usize, y as usize)?;
-                        term.write_str(&format!("{}", c))?;
+                        if let Some(term) = term {
+                            term.move_cursor_to(x as usize, y as usize)?;
+                            term.write_str(&format!("{}", c))?;
+                        }
+                    }
+                    if term.is_some() {
+                        std::thread::sleep(std::time::Duration::from_millis(1));
                     }
-                    std::thread::sleep(std::time::Duration::from_millis(1));
                     outputs.clear();
                 }
             }
-            Event::InputRequired => match ball.position.0.cmp(&paddle.position.0) {
-                Ordering::Less => cpu.input.push_back(-1),
-                Ordering::Equal => cpu.input.push_back(0),
-                Ordering::Greater => cpu.input.push_back(1),
-            },
+            Event::InputRequired => joystick.supply(cpu, &ball, &paddle)?,
         }
     }
-    term.clear_screen()?;
+    if let Joystick::Replay(player) = joystick {
+        if player.remaining() > 0 {
+            return Err(invalid_data(ReplayError::Unused {
+                remaining: player.remaining(),
+            }));
+        }
+    }
+    if let Some(term) = term {
+        term.clear_screen()?;
+    }
     Ok(score)
 }
 
+// Usage: day13 [--record FILE | --replay FILE] [--headless]
 fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|i| args.get(i + 1))
+    };
+    let headless = args.iter().any(|arg| arg == "--headless");
+
     let code = fs::read_to_string("./input/day13.in")?;
     let mut cpu = IntCodeCpu::from_code(&code);
     cpu.run();
@@ -110,10 +166,46 @@ fn main() -> io::Result<()> {
         .filter(|(_, _, id)| TileID::from(**id) == TileID::Block)
         .count();
 
+    let mut joystick = match flag("--replay") {
+        Some(path) => Joystick::Replay(Player::new(Replay::load(path)?)),
+        None => Joystick::Follow(flag("--record").map(|_| Recorder::new())),
+    };
+    let term = Term::stdout();
     cpu = IntCodeCpu::from_code(&code);
-    let score = play(&mut cpu)?;
+    let score = play(&mut cpu, Some(&term).filter(|_| !headless), &mut joystick)?;
+    if let (Some(path), Joystick::Follow(Some(recorder))) = (flag("--record"), joystick) {
+        recorder.finish().save(path)?;
+    }
     println!("p1: {}", num_blocks);
     println!("p2: {}", score);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_replay() {
+        let code = fs::read_to_string("./input/day13.in").unwrap();
+        let mut joystick = Joystick::Follow(Some(Recorder::new()));
+        let score = play(&mut IntCodeCpu::from_code(&code), None, &mut joystick).unwrap();
+        let replay = match joystick {
+            Joystick::Follow(Some(recorder)) => recorder.finish(),
+            _ => unreachable!(),
+        };
+        assert!(!replay.entries.is_empty());
+
+        let mut joystick = Joystick::Replay(Player::new(replay.clone()));
+        let replayed = play(&mut IntCodeCpu::from_code(&code), None, &mut joystick).unwrap();
+        assert_eq!(replayed, score);
+
+        // a different first move makes the program ask for input at other steps
+        let mut changed = replay;
+        changed.entries[0].value = if changed.entries[0].value == 0 { 1 } else { 0 };
+        let mut joystick = Joystick::Replay(Player::new(changed));
+        let err = play(&mut IntCodeCpu::from_code(&code), None, &mut joystick).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
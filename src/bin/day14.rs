@@ -55,7 +55,7 @@ fn get_max_fuel(reactions: &Reaction, stock: &mut Stock, avail_ores: isize) -> i
 
     while lb != ub {
         let m = (lb + ub + 1) / 2;
-        match get_num_ores(&reactions, stock, "FUEL", m).cmp(&avail_ores) {
+        match get_num_ores(reactions, stock, "FUEL", m).cmp(&avail_ores) {
             Ordering::Equal => return m,
             Ordering::Greater => ub = (lb + ub) / 2,
             Ordering::Less => lb = m,
@@ -1,5 +1,5 @@
+use aoc2019::intcode::explore::{explore, Action, Explored, Strategy};
 use aoc2019::intcode::IntCodeCpu;
-use std::collections::HashSet;
 use std::fs;
 use std::io;
 
@@ -41,121 +41,50 @@ impl From<i64> for Status {
     }
 }
 
-struct Position {
-    steps: i64,
-    cpu: IntCodeCpu,
-    x: i64,
-    y: i64,
-}
-
-fn find_oxygen_system(cpu: IntCodeCpu) -> Position {
-    let mut positions = vec![Position {
-        steps: 0,
-        cpu: cpu,
-        x: 0,
-        y: 0,
-    }];
-
-    let mut visited = HashSet::new();
-
-    loop {
-        let pos = positions.remove(0);
-
-        for direction in 1..5 {
-            let mut new_x = pos.x;
-            let mut new_y = pos.y;
-            match Direction::from(direction) {
-                Direction::North => new_y -= 1,
-                Direction::South => new_y += 1,
-                Direction::West => new_x -= 1,
-                Direction::East => new_x += 1,
-            }
-
-            if !visited.insert((new_x, new_y)) {
-                continue;
-            }
-
-            let mut new_cpu = pos.cpu.clone();
-            new_cpu.input.push_back(direction);
-            let output = new_cpu.run_until_output().unwrap();
-            match Status::from(output) {
-                Status::HitWall => {
-                    // hit the wall
-                }
-                Status::Moved => positions.push(Position {
-                    cpu: new_cpu,
-                    steps: pos.steps + 1,
-                    x: new_x,
-                    y: new_y,
-                }),
+type Point = (i64, i64);
+
+// Both parts are breadth first searches over the droid's position: the first from the start
+// until the oxygen system is found, the second from the oxygen system over the whole map.
+fn explore_from(cpu: IntCodeCpu, start: Point) -> (Explored<Point>, Option<Point>) {
+    let actions: Vec<Action> = (1..5)
+        .map(|direction| Action::new(vec![direction]))
+        .collect();
+    let mut oxygen_system = None;
+    let explored = explore(
+        cpu,
+        start,
+        &actions,
+        Strategy::BreadthFirst,
+        |&(x, y), action, outputs| {
+            let (x, y) = match Direction::from(action.input[0]) {
+                Direction::North => (x, y - 1),
+                Direction::South => (x, y + 1),
+                Direction::West => (x - 1, y),
+                Direction::East => (x + 1, y),
+            };
+            match Status::from(outputs[0]) {
+                Status::HitWall => None,
+                Status::Moved => Some((x, y)),
                 Status::Reached => {
-                    return Position {
-                        cpu: new_cpu,
-                        steps: pos.steps + 1,
-                        x: new_x,
-                        y: new_y,
-                    }
+                    oxygen_system = Some((x, y));
+                    Some((x, y))
                 }
             }
-        }
-    }
-}
-
-fn fill_with_oxygen(cpu: IntCodeCpu) -> i64 {
-    let mut positions = vec![Position {
-        steps: 0,
-        cpu: cpu,
-        x: 0,
-        y: 0,
-    }];
-
-    let mut visited = HashSet::new();
-    let mut steps = 0;
-
-    while !positions.is_empty() {
-        let pos = positions.remove(0);
-        steps = pos.steps;
-
-        for direction in 1..5 {
-            let mut new_x = pos.x;
-            let mut new_y = pos.y;
-            match Direction::from(direction) {
-                Direction::North => new_y -= 1,
-                Direction::South => new_y += 1,
-                Direction::West => new_x -= 1,
-                Direction::East => new_x += 1,
-            }
-
-            if !visited.insert((new_x, new_y)) {
-                continue;
-            }
-
-            let mut new_cpu = pos.cpu.clone();
-            new_cpu.input.push_back(direction);
-            let output = new_cpu.run_until_output().unwrap();
-            match Status::from(output) {
-                Status::HitWall => {
-                    // hit the wall
-                }
-                Status::Moved | Status::Reached => positions.push(Position {
-                    cpu: new_cpu,
-                    steps: pos.steps + 1,
-                    x: new_x,
-                    y: new_y,
-                }),
-            }
-        }
-    }
-    steps
+        },
+    );
+    (explored, oxygen_system)
 }
 
 fn main() -> io::Result<()> {
     let code = fs::read_to_string("./input/day15.in")?;
     let cpu = IntCodeCpu::from_code(&code);
 
-    let position = find_oxygen_system(cpu);
+    let (explored, oxygen_system) = explore_from(cpu, (0, 0));
+    let oxygen_system = oxygen_system.expect("no oxygen system found");
+    let cpu = explored.cpu(&oxygen_system).unwrap().clone();
+    let (filled, _) = explore_from(cpu, oxygen_system);
 
-    println!("p1: {}", position.steps);
-    println!("p2: {}", fill_with_oxygen(position.cpu));
+    println!("p1: {}", explored.distance(&oxygen_system).unwrap());
+    println!("p2: {}", filled.max_distance());
     Ok(())
 }
@@ -1,8 +1,8 @@
 use std::fs;
 use std::io;
 
-fn calc_fft(input: &mut Vec<i8>, cycles: usize) -> isize {
-    let base_pattern = vec![0, 1, 0, -1];
+fn calc_fft(input: &mut [i8], cycles: usize) -> isize {
+    let base_pattern = [0, 1, 0, -1];
     for _ in 0..cycles {
         for i in 0..input.len() - 1 {
             input[i] = (input
@@ -34,7 +34,7 @@ fn get_offset(input: &[i8]) -> usize {
 }
 
 fn calc_fft_with_offset(input: &[i8], cycles: usize) -> isize {
-    let offset: usize = get_offset(&input);
+    let offset: usize = get_offset(input);
 
     let mut digits: Vec<i8> = input
         .iter()
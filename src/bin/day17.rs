@@ -87,7 +87,7 @@ fn part2(cpu: &mut IntCodeCpu) -> i64 {
 
 fn main() -> io::Result<()> {
     let code = fs::read_to_string("./input/day17.in")?;
-    let cpu = IntCodeCpu::from_code(&code.trim());
+    let cpu = IntCodeCpu::from_code(code.trim());
 
     println!("p1: {}", part1(&mut cpu.clone()));
     println!("p2: {}", part2(&mut cpu.clone()));
@@ -123,7 +123,7 @@ fn get_possible_moves(
 fn collect_keys(map: &[Vec<MapObject>], from: (usize, usize)) -> usize {
     let mut pos_to_visit = VecDeque::new();
     let mut visited = HashMap::new();
-    let mut sum_steps = std::usize::MAX;
+    let mut sum_steps = usize::MAX;
 
     pos_to_visit.push_back((from, 0, KeyRing::new()));
     while let Some((pos, steps, keys)) = pos_to_visit.pop_front() {
@@ -151,7 +151,7 @@ fn collect_keys(map: &[Vec<MapObject>], from: (usize, usize)) -> usize {
 fn collect_keys_with_4_robots(map: &[Vec<MapObject>], from: Vec<(usize, usize)>) -> usize {
     let mut pos_to_visit = VecDeque::new();
     let mut visited = HashMap::new();
-    let mut sum_steps = std::usize::MAX;
+    let mut sum_steps = usize::MAX;
 
     pos_to_visit.push_back((from, 0, KeyRing::new()));
     while let Some((pos, steps, keys)) = pos_to_visit.pop_front() {
@@ -276,7 +276,7 @@ fn collect_keys_with_4_robots(map: &[Vec<MapObject>], from: Vec<(usize, usize)>)
     sum_steps
 }
 
-fn manipulate_map(input: &String) -> String {
+fn manipulate_map(input: &str) -> String {
     let entrance = find_entrances(input);
     assert!(entrance.len() == 1);
     let (ex, ey) = *entrance.first().unwrap();
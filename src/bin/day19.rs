@@ -57,7 +57,7 @@ fn get_ship_location(cpu: &IntCodeCpu, ship_width: usize, ship_len: usize) -> (u
 
 fn main() -> io::Result<()> {
     let code = fs::read_to_string("./input/day19.in")?;
-    let cpu = IntCodeCpu::from_code(&code.trim());
+    let cpu = IntCodeCpu::from_code(code.trim());
 
     println!("p1: {}", num_affected_points(&cpu, 50, 50));
 
@@ -0,0 +1,35 @@
+use aoc2019::intcode::image::{self, Format};
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+// Usage: intcode_convert <input> <output> <text|lines|binary>
+// Converts a program image of any supported format into the given format. Comments in text
+// images are not preserved.
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 3 {
+        eprintln!("usage: intcode_convert <input> <output> <text|lines|binary>");
+        process::exit(1);
+    }
+    let format = args[2].parse::<Format>().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let input = fs::read(&args[0])?;
+    let program = image::load(&input).unwrap_or_else(|e| {
+        eprintln!("{}: {}", args[0], e);
+        process::exit(1);
+    });
+    fs::write(&args[1], image::encode(&program, format))?;
+    eprintln!(
+        "{}: {} values, {:?} -> {:?}",
+        args[1],
+        program.len(),
+        image::detect(&input),
+        format
+    );
+    Ok(())
+}
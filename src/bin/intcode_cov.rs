@@ -0,0 +1,60 @@
+use aoc2019::intcode::coverage::Coverage;
+use aoc2019::intcode::{Event, IntCodeCpu};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::process;
+
+fn usage() -> ! {
+    eprintln!("usage: intcode_cov <program> <out> [INPUTS]...");
+    process::exit(1);
+}
+
+fn parse_values(s: &str) -> Vec<i64> {
+    s.split(',')
+        .filter(|x| !x.is_empty())
+        .map(|x| x.trim().parse::<i64>().unwrap_or_else(|_| usage()))
+        .collect()
+}
+
+// Usage: intcode_cov <program> <out> [INPUTS]...
+// Runs the program once per comma separated input list (or once without input), merges the
+// coverage of all runs and writes an annotated listing to <out>.lst and an lcov tracefile to
+// <out>.info. A run ends when the program halts or asks for more input than given.
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 {
+        usage();
+    }
+    let cpu = IntCodeCpu::from_image(&fs::read(&args[0])?).unwrap_or_else(|e| {
+        eprintln!("{}: {}", args[0], e);
+        process::exit(1);
+    });
+    let program = cpu.memory().to_vec();
+
+    let mut runs: Vec<Vec<i64>> = args[2..].iter().map(|run| parse_values(run)).collect();
+    if runs.is_empty() {
+        runs.push(vec![]);
+    }
+
+    let mut coverage = Coverage::default();
+    for inputs in runs {
+        let mut cpu = cpu.clone();
+        cpu.enable_coverage();
+        cpu.input.extend(inputs);
+        while let Event::OutputAvailable(_) = cpu.run_until_event() {}
+        coverage.merge(&cpu.take_coverage().unwrap());
+    }
+
+    coverage.write_listing(
+        &program,
+        &mut BufWriter::new(File::create(format!("{}.lst", args[1]))?),
+    )?;
+    coverage.write_lcov(
+        &program,
+        &args[0],
+        &mut BufWriter::new(File::create(format!("{}.info", args[1]))?),
+    )?;
+    println!("{} instructions executed", coverage.executed().count());
+    Ok(())
+}
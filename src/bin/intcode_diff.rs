@@ -0,0 +1,101 @@
+use aoc2019::intcode::memdiff::{self, Filter, Search};
+use aoc2019::intcode::{Event, IntCodeCpu};
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+// Usage: intcode_diff <program> [--poke ADDR=VAL]... [INPUTS[:FILTER]]...
+//
+// Runs the program until it asks for input and then feeds it one step of comma separated inputs
+// at a time. After every step the changed memory cells are listed. If a filter is given, only
+// cells that matched the filters of all filtered steps so far are listed, e.g.
+//
+//     intcode_diff input/day13.in --poke 0=2 0 -1:dec -1:dec 1:inc
+fn usage() -> ! {
+    eprintln!("usage: intcode_diff <program> [--poke ADDR=VAL]... [INPUTS[:FILTER]]...");
+    eprintln!("filters: changed, unchanged, inc, dec, +N, -N, =N");
+    process::exit(1);
+}
+
+fn parse_values(s: &str) -> Vec<i64> {
+    s.split(',')
+        .filter(|x| !x.is_empty())
+        .map(|x| x.trim().parse::<i64>().unwrap_or_else(|_| usage()))
+        .collect()
+}
+
+fn run_until_input(cpu: &mut IntCodeCpu) -> bool {
+    loop {
+        match cpu.run_until_event() {
+            Event::OutputAvailable(_) => cpu.output.clear(),
+            Event::InputRequired => return true,
+            Event::Halted => return false,
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut args = env::args().skip(1);
+    let image = fs::read(args.next().unwrap_or_else(|| usage()))?;
+    let mut cpu = IntCodeCpu::from_image(&image).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let mut steps = vec![];
+    while let Some(arg) = args.next() {
+        if arg == "--poke" {
+            let poke = args.next().unwrap_or_else(|| usage());
+            let mut parts = poke.splitn(2, '=');
+            let addr = parts.next().and_then(|a| a.parse::<usize>().ok());
+            let val = parts.next().and_then(|v| v.parse::<i64>().ok());
+            match (addr, val) {
+                (Some(addr), Some(val)) => cpu.poke_memory(addr, val),
+                _ => usage(),
+            }
+        } else {
+            let mut parts = arg.splitn(2, ':');
+            let inputs = parse_values(parts.next().unwrap());
+            let filter = parts.next().map(|f| {
+                f.parse::<Filter>().unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    usage()
+                })
+            });
+            steps.push((inputs, filter));
+        }
+    }
+
+    let mut running = run_until_input(&mut cpu);
+    let mut search = Search::new(&cpu);
+    let mut filtered = false;
+    for (i, (inputs, filter)) in steps.into_iter().enumerate() {
+        if !running {
+            println!("program halted before step {}", i + 1);
+            break;
+        }
+        let before = cpu.clone();
+        cpu.input.extend(inputs);
+        running = run_until_input(&mut cpu);
+
+        println!("step {}:", i + 1);
+        let changes = match filter {
+            Some(filter) => {
+                filtered = true;
+                search.update(&cpu, filter)
+            }
+            None => {
+                search.snapshot(&cpu);
+                memdiff::diff(&before, &cpu)
+                    .into_iter()
+                    .filter(|c| !filtered || search.candidates().contains(&c.addr))
+                    .collect()
+            }
+        };
+        for change in changes {
+            println!("{}", change);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use aoc2019::intcode::gdbstub::GdbStub;
+use aoc2019::intcode::IntCodeCpu;
+use std::env;
+use std::fs;
+use std::io;
+use std::net::TcpListener;
+use std::process;
+
+fn usage() -> ! {
+    eprintln!("usage: intcode_gdb <program> [port] [INPUTS]");
+    process::exit(1);
+}
+
+// Usage: intcode_gdb <program> [port] [INPUTS]
+// Waits for a debugger on 127.0.0.1:<port> (default 1234) and serves the program to it, e.g.
+//
+//     (gdb) target remote :1234
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.len() > 3 {
+        usage();
+    }
+    let mut cpu = IntCodeCpu::from_image(&fs::read(&args[0])?).unwrap_or_else(|e| {
+        eprintln!("{}: {}", args[0], e);
+        process::exit(1);
+    });
+    let port = args
+        .get(1)
+        .map_or(1234, |p| p.parse::<u16>().unwrap_or_else(|_| usage()));
+    if let Some(inputs) = args.get(2) {
+        cpu.input.extend(
+            inputs
+                .split(',')
+                .map(|x| x.trim().parse::<i64>().unwrap_or_else(|_| usage())),
+        );
+    }
+
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    eprintln!("waiting for debugger on {}", listener.local_addr()?);
+    let (stream, addr) = listener.accept()?;
+    eprintln!("debugger connected from {}", addr);
+
+    let mut stub = GdbStub::new(cpu);
+    stub.serve(stream)?;
+    eprintln!("debugger disconnected");
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use aoc2019::intcode::{image, optimizer};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+// Usage: intcode_opt [program]
+// Reads the program image from the given file or stdin, prints the applied rewrites to stderr
+// and the optimized program to stdout.
+fn main() -> io::Result<()> {
+    let mut input = vec![];
+    match env::args().nth(1) {
+        Some(path) => input = fs::read(path)?,
+        None => {
+            io::stdin().read_to_end(&mut input)?;
+        }
+    }
+    let program = image::load(&input).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    match optimizer::optimize(&program) {
+        Ok(optimized) => {
+            for rewrite in &optimized.rewrites {
+                eprintln!("{}", rewrite);
+            }
+            eprintln!("{} rewrites applied", optimized.rewrites.len());
+            io::stdout().write_all(&image::encode(&optimized.program, image::Format::Text))?;
+        }
+        Err(e) => {
+            eprintln!("not optimized: {}", e);
+            io::stdout().write_all(&image::encode(&program, image::Format::Text))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use aoc2019::intcode::symbolic::{End, Expr, SymbolicCpu};
+use aoc2019::intcode::IntCodeCpu;
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+// Usage: intcode_sym <program> [ADDR=NAME]... [--input NAME]... [--max-steps N] [--max-paths N]
+//
+// Runs the program symbolically. ADDR=NAME replaces a memory cell with a symbol, --input NAME
+// supplies the next input; both accept numbers for concrete values. Further inputs are read as
+// symbols in0, in1, ... For every path the branch conditions, outputs and the final value of
+// every cell the program set to an expression depending on a symbol are printed, e.g.
+//
+//     intcode_sym input/day02.in 1=noun 2=verb
+//     intcode_sym input/day19.in --input x --input y
+fn usage() -> ! {
+    eprintln!(
+        "usage: intcode_sym <program> [ADDR=NAME]... [--input NAME]... \
+         [--max-steps N] [--max-paths N]"
+    );
+    process::exit(1);
+}
+
+fn parse_expr(s: &str) -> Expr {
+    match s.parse::<i64>() {
+        Ok(val) => Expr::Const(val),
+        Err(_) => Expr::sym(s),
+    }
+}
+
+fn parse_limit(arg: Option<String>) -> usize {
+    arg.and_then(|n| n.parse().ok()).unwrap_or_else(|| usage())
+}
+
+fn main() -> io::Result<()> {
+    let mut args = env::args().skip(1);
+    let image = fs::read(args.next().unwrap_or_else(|| usage()))?;
+    let program = IntCodeCpu::from_image(&image).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let mut cpu = SymbolicCpu::new(program.memory());
+    let (mut max_steps, mut max_paths) = (100_000, 256);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => cpu.push_input(parse_expr(&args.next().unwrap_or_else(|| usage()))),
+            "--max-steps" => max_steps = parse_limit(args.next()),
+            "--max-paths" => max_paths = parse_limit(args.next()),
+            _ => {
+                let mut parts = arg.splitn(2, '=');
+                let addr = parts.next().and_then(|a| a.parse::<usize>().ok());
+                match (addr, parts.next()) {
+                    (Some(addr), Some(name)) => cpu.set_cell(addr, parse_expr(name)),
+                    _ => usage(),
+                }
+            }
+        }
+    }
+    cpu.set_limits(max_steps, max_paths);
+    let initial = cpu.memory().to_vec();
+
+    let paths = cpu.run();
+    for (i, path) in paths.iter().enumerate() {
+        match &path.end {
+            End::Halted => println!("path {}: halted", i),
+            End::StepLimit => println!("path {}: step limit reached", i),
+            End::PathLimit => println!("path {}: path limit reached", i),
+            End::Error(e) => println!("path {}: {}", i, e),
+        }
+        for cond in &path.conditions {
+            println!("  if {}", cond);
+        }
+        for (j, out) in path.outputs.iter().enumerate() {
+            println!("  out{} = {}", j, out);
+        }
+        for (addr, expr) in path.symbolic_cells() {
+            if initial.get(addr) != Some(expr) {
+                println!("  [{}] = {}", addr, expr);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,307 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// RGBA images that can be saved as binary PPM (P6), binary PGM (P5) or PNG. PPM and PGM have
+// no alpha channel and write the colour of transparent pixels as it is. The PNG encoder uses
+// stored, i.e. uncompressed, deflate blocks.
+
+pub type Rgba = [u8; 4];
+
+pub const BLACK: Rgba = [0, 0, 0, 255];
+pub const WHITE: Rgba = [255, 255, 255, 255];
+// Grey, so it stays visible in formats without alpha
+pub const TRANSPARENT: Rgba = [128, 128, 128, 0];
+
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+// Largest payload of a stored deflate block
+const STORED_BLOCK: usize = 65535;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Ppm,
+    Pgm,
+    Png,
+}
+
+impl Format {
+    // The format given by the file extension
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(Format::Ppm),
+            "pgm" => Some(Format::Pgm),
+            "png" => Some(Format::Png),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bitmap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgba>,
+}
+
+impl Bitmap {
+    pub fn new(width: usize, height: usize, fill: Rgba) -> Bitmap {
+        Bitmap {
+            width,
+            height,
+            pixels: vec![fill; width * height],
+        }
+    }
+
+    pub fn from_fn<F: Fn(usize, usize) -> Rgba>(width: usize, height: usize, f: F) -> Bitmap {
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+        Bitmap {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Rgba {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, colour: Rgba) {
+        self.pixels[y * self.width + x] = colour;
+    }
+
+    // Every pixel becomes a factor x factor square
+    pub fn scale(&self, factor: usize) -> Bitmap {
+        Bitmap::from_fn(self.width * factor, self.height * factor, |x, y| {
+            self.get(x / factor, y / factor)
+        })
+    }
+
+    pub fn write_ppm<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        let data: Vec<u8> = self.pixels.iter().flat_map(|p| p[..3].to_vec()).collect();
+        out.write_all(&data)
+    }
+
+    pub fn write_pgm<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "P5\n{} {}\n255\n", self.width, self.height)?;
+        let data: Vec<u8> = self.pixels.iter().map(|p| luma(*p)).collect();
+        out.write_all(&data)
+    }
+
+    pub fn write_png<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(PNG_SIGNATURE)?;
+
+        let mut header = vec![];
+        header.extend(&(self.width as u32).to_be_bytes());
+        header.extend(&(self.height as u32).to_be_bytes());
+        // 8 bits per channel, RGBA, deflate, adaptive filters, no interlace
+        header.extend(&[8, 6, 0, 0, 0]);
+        write_chunk(out, b"IHDR", &header)?;
+
+        // every scanline starts with filter type 0, none
+        let mut raw = Vec::with_capacity(self.height * (self.width * 4 + 1));
+        for row in self.pixels.chunks(self.width.max(1)).take(self.height) {
+            raw.push(0);
+            raw.extend(row.iter().flatten());
+        }
+        write_chunk(out, b"IDAT", &zlib_stored(&raw))?;
+        write_chunk(out, b"IEND", &[])
+    }
+
+    pub fn write<W: Write>(&self, format: Format, out: &mut W) -> io::Result<()> {
+        match format {
+            Format::Ppm => self.write_ppm(out),
+            Format::Pgm => self.write_pgm(out),
+            Format::Png => self.write_png(out),
+        }
+    }
+
+    // Scales the image and saves it in the format given by the file extension
+    pub fn save<P: AsRef<Path>>(&self, path: P, scale: usize) -> io::Result<()> {
+        let path = path.as_ref();
+        let format = Format::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}: expected a .ppm, .pgm or .png file", path.display()),
+            )
+        })?;
+        let mut out = BufWriter::new(File::create(path)?);
+        self.scale(scale).write(format, &mut out)?;
+        out.flush()
+    }
+}
+
+fn luma(p: Rgba) -> u8 {
+    ((p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000) as u8
+}
+
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data));
+    out.write_all(&crc.to_be_bytes())
+}
+
+// zlib stream with the data in stored deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // deflate with a 32K window, no preset dictionary, header checksum to a multiple of 31
+    let mut z = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = if data.is_empty() {
+        vec![data]
+    } else {
+        data.chunks(STORED_BLOCK).collect()
+    };
+    for (i, block) in blocks.iter().enumerate() {
+        z.push((i + 1 == blocks.len()) as u8);
+        let len = block.len() as u16;
+        z.extend(&len.to_le_bytes());
+        z.extend(&(!len).to_le_bytes());
+        z.extend(*block);
+    }
+    z.extend(&adler32(data).to_be_bytes());
+    z
+}
+
+fn crc32<'a, I: IntoIterator<Item = &'a u8>>(data: I) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn checkerboard() -> Bitmap {
+        Bitmap::from_fn(3, 2, |x, y| match (x + y) % 3 {
+            0 => BLACK,
+            1 => WHITE,
+            _ => TRANSPARENT,
+        })
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn test_scale() {
+        let image = checkerboard().scale(2);
+        assert_eq!((image.width(), image.height()), (6, 4));
+        assert_eq!(image.get(1, 1), BLACK);
+        assert_eq!(image.get(2, 1), WHITE);
+        assert_eq!(image.get(5, 3), BLACK);
+    }
+
+    #[test]
+    fn test_netpbm() {
+        let mut ppm = vec![];
+        checkerboard().write_ppm(&mut ppm).unwrap();
+        assert!(ppm.starts_with(b"P6\n3 2\n255\n"));
+        assert_eq!(&ppm[11..20], &[0, 0, 0, 255, 255, 255, 128, 128, 128]);
+        assert_eq!(ppm.len(), 11 + 18);
+
+        let mut pgm = vec![];
+        checkerboard().write_pgm(&mut pgm).unwrap();
+        assert_eq!(pgm, b"P5\n3 2\n255\n\x00\xff\x80\xff\x80\x00");
+    }
+
+    // Reads the chunks back, checking their CRCs, and unpacks the stored blocks of IDAT
+    fn decode_png(png: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        assert_eq!(&png[..8], PNG_SIGNATURE);
+        let (mut header, mut idat) = (vec![], vec![]);
+        let mut pos = 8;
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let kind = &png[pos + 4..pos + 8];
+            let data = &png[pos + 8..pos + 8 + len];
+            let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+            assert_eq!(crc, crc32(kind.iter().chain(data)));
+            match kind {
+                b"IHDR" => header = data.to_vec(),
+                b"IDAT" => idat.extend(data),
+                _ => (),
+            }
+            pos += 12 + len;
+        }
+
+        let mut raw = vec![];
+        let mut pos = 2;
+        loop {
+            let last = idat[pos] & 1 == 1;
+            let len = u16::from_le_bytes([idat[pos + 1], idat[pos + 2]]) as usize;
+            let nlen = u16::from_le_bytes([idat[pos + 3], idat[pos + 4]]) as usize;
+            assert_eq!(len, !nlen & 0xffff);
+            raw.extend(&idat[pos + 5..pos + 5 + len]);
+            pos += 5 + len;
+            if last {
+                break;
+            }
+        }
+        assert_eq!(&idat[pos..], &adler32(&raw).to_be_bytes());
+        (header, raw)
+    }
+
+    #[test]
+    fn test_png() {
+        let mut png = vec![];
+        checkerboard().write_png(&mut png).unwrap();
+        let (header, raw) = decode_png(&png);
+        assert_eq!(header, [0, 0, 0, 3, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+        assert_eq!(raw.len(), 2 * (1 + 3 * 4));
+        assert_eq!(&raw[..5], &[0, 0, 0, 0, 255]);
+        assert_eq!(&raw[9..13], &TRANSPARENT);
+
+        // more than one stored block
+        let large = Bitmap::from_fn(300, 200, |x, y| [x as u8, y as u8, 0, 255]);
+        let mut png = vec![];
+        large.write_png(&mut png).unwrap();
+        let (_, raw) = decode_png(&png);
+        assert_eq!(raw.len(), 200 * (1 + 300 * 4));
+        let (x, y) = (43, 5);
+        assert_eq!(&raw[y * 1201 + 1 + x * 4..][..4], &[43, 5, 0, 255]);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(Format::from_path(Path::new("a/b.PNG")), Some(Format::Png));
+        assert_eq!(Format::from_path(Path::new("b.pgm")), Some(Format::Pgm));
+        assert_eq!(Format::from_path(Path::new("b.gif")), None);
+        assert_eq!(Format::from_path(Path::new("ppm")), None);
+    }
+}
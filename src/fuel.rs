@@ -0,0 +1,193 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+// Fuel required to launch a module, as given by the rocket equation. Masses below 6 would need
+// negative fuel and are treated as needing none.
+pub fn fuel(mass: u64) -> u64 {
+    (mass / 3).saturating_sub(2)
+}
+
+// Fuel for the module plus the fuel for that fuel, until the additional amount is zero
+pub fn total_fuel(mass: u64) -> u64 {
+    let mut total = 0;
+    let mut added = fuel(mass);
+    while added > 0 {
+        total += added;
+        added = fuel(added);
+    }
+    total
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub value: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid mass {:?} in line {}", self.value, self.line)
+    }
+}
+
+impl Error for ParseError {}
+
+// Parses one mass per line, empty lines are skipped
+pub fn parse_masses(input: &str) -> Result<Vec<u64>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            line.trim().parse::<u64>().map_err(|_| ParseError {
+                line: i + 1,
+                value: line.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModuleFuel {
+    pub mass: u64,
+    pub fuel: u64,
+    pub fuel_for_fuel: u64,
+}
+
+impl ModuleFuel {
+    pub fn new(mass: u64) -> ModuleFuel {
+        let fuel = fuel(mass);
+        ModuleFuel {
+            mass,
+            fuel,
+            fuel_for_fuel: total_fuel(mass) - fuel,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.fuel + self.fuel_for_fuel
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat {
+    Table,
+    Csv,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ReportFormat, String> {
+        match s {
+            "table" => Ok(ReportFormat::Table),
+            "csv" => Ok(ReportFormat::Csv),
+            _ => Err(format!("unknown report format: {}", s)),
+        }
+    }
+}
+
+// Writes one row per module; the table ends with a row of totals
+pub fn write_report<W: Write>(
+    modules: &[ModuleFuel],
+    format: ReportFormat,
+    out: &mut W,
+) -> io::Result<()> {
+    match format {
+        ReportFormat::Csv => {
+            writeln!(out, "mass,fuel,fuel_for_fuel,total")?;
+            for m in modules {
+                writeln!(
+                    out,
+                    "{},{},{},{}",
+                    m.mass,
+                    m.fuel,
+                    m.fuel_for_fuel,
+                    m.total()
+                )?;
+            }
+        }
+        ReportFormat::Table => {
+            let row = |out: &mut W, cells: [&dyn fmt::Display; 4]| {
+                writeln!(
+                    out,
+                    "{:>10} {:>10} {:>14} {:>10}",
+                    cells[0], cells[1], cells[2], cells[3]
+                )
+            };
+            row(out, [&"mass", &"fuel", &"fuel for fuel", &"total"])?;
+            for m in modules {
+                row(out, [&m.mass, &m.fuel, &m.fuel_for_fuel, &m.total()])?;
+            }
+            let sum = |f: fn(&ModuleFuel) -> u64| modules.iter().map(f).sum::<u64>();
+            row(
+                out,
+                [
+                    &"sum",
+                    &sum(|m| m.fuel),
+                    &sum(|m| m.fuel_for_fuel),
+                    &sum(|m| m.total()),
+                ],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuel() {
+        assert_eq!(fuel(12), 2);
+        assert_eq!(fuel(14), 2);
+        assert_eq!(fuel(1969), 654);
+        assert_eq!(fuel(100_756), 33583);
+        for mass in 0..9 {
+            assert_eq!(fuel(mass), 0);
+        }
+        assert_eq!(fuel(9), 1);
+    }
+
+    #[test]
+    fn test_total_fuel() {
+        assert_eq!(total_fuel(14), 2);
+        assert_eq!(total_fuel(1969), 966);
+        assert_eq!(total_fuel(100_756), 50346);
+        assert_eq!(total_fuel(5), 0);
+    }
+
+    #[test]
+    fn test_parse_masses() {
+        assert_eq!(parse_masses("12\n\n 14\n"), Ok(vec![12, 14]));
+        assert_eq!(
+            parse_masses("12\n14\n-3\n"),
+            Err(ParseError {
+                line: 3,
+                value: "-3".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let modules: Vec<_> = [14, 1969].iter().map(|m| ModuleFuel::new(*m)).collect();
+        let mut csv = vec![];
+        write_report(&modules, ReportFormat::Csv, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "mass,fuel,fuel_for_fuel,total\n14,2,0,2\n1969,654,312,966\n"
+        );
+
+        let mut table = vec![];
+        write_report(&modules, ReportFormat::Table, &mut table).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        assert_eq!(
+            table.lines().last(),
+            Some("       sum        656            312        968")
+        );
+    }
+}
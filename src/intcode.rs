@@ -1,4 +1,21 @@
+pub mod amplifiers;
+pub mod coverage;
+mod disasm;
+pub mod explore;
+pub mod gdbstub;
+pub mod image;
+pub mod memdiff;
+pub mod optimizer;
+pub mod peripheral;
+pub mod replay;
+pub mod symbolic;
+
+use coverage::Coverage;
+use peripheral::{Peripherals, SharedPeripheral};
 use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
 
 #[derive(Clone, Debug)]
 pub struct IntCodeCpu {
@@ -8,8 +25,42 @@ pub struct IntCodeCpu {
     pub input: VecDeque<i64>,
     pub output: VecDeque<i64>,
     memory: Vec<i64>,
+    peripherals: Peripherals,
+    arithmetic: Arithmetic,
+    overflow: Option<Overflow>,
+    coverage: Option<Coverage>,
+    steps: u64,
+}
+
+// Policy for ADD and MUL results that do not fit into an i64
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arithmetic {
+    Wrap,
+    Saturate,
+    Trap,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Overflow {
+    pub ip: usize,
+    pub opcode: &'static str,
+    pub src1: i64,
+    pub src2: i64,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "arithmetic overflow in {} {}, {} at ip {}",
+            self.opcode, self.src1, self.src2, self.ip
+        )
+    }
 }
 
+impl Error for Overflow {}
+
+#[allow(clippy::upper_case_acronyms)]
 enum Instruction {
     ADD { src1: i64, src2: i64, dst: i64 },
     MUL { src1: i64, src2: i64, dst: i64 },
@@ -39,19 +90,69 @@ pub enum Event {
 
 impl IntCodeCpu {
     pub fn from_code(code: &str) -> IntCodeCpu {
+        IntCodeCpu::from_memory(
+            code.split(',')
+                .map(|x| x.trim().parse::<i64>().unwrap())
+                .collect(),
+        )
+    }
+
+    pub fn from_memory(memory: Vec<i64>) -> IntCodeCpu {
         IntCodeCpu {
             ip: 0,
             rbp: 0,
             running: false,
             input: VecDeque::new(),
             output: VecDeque::new(),
-            memory: code
-                .split(',')
-                .map(|x| x.trim().parse::<i64>().unwrap())
-                .collect(),
+            memory,
+            peripherals: Peripherals::default(),
+            arithmetic: Arithmetic::Wrap,
+            overflow: None,
+            coverage: None,
+            steps: 0,
         }
     }
 
+    pub fn memory(&self) -> &[i64] {
+        &self.memory
+    }
+
+    // Maps a device into the address range, panics if the range overlaps another device
+    pub fn attach_peripheral(&mut self, range: Range<usize>, device: SharedPeripheral) {
+        self.peripherals.attach(range, device);
+    }
+
+    pub fn detach_peripherals(&mut self) {
+        self.peripherals.detach_all();
+    }
+
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+    }
+
+    // The overflow that halted the cpu in Arithmetic::Trap mode
+    pub fn overflow(&self) -> Option<&Overflow> {
+        self.overflow.as_ref()
+    }
+
+    // Starts recording executed instructions and branch directions
+    pub fn enable_coverage(&mut self) {
+        self.coverage.get_or_insert_with(Coverage::default);
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    // Number of instructions executed so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
     // Set cpu to running state manually
     pub fn set_running(&mut self) {
         self.running = true;
@@ -81,6 +182,15 @@ impl IntCodeCpu {
         }
     }
 
+    // Halts on opcode halt or on an arithmetic overflow in Arithmetic::Trap mode
+    pub fn try_run(&mut self) -> Result<(), Overflow> {
+        self.run();
+        match self.overflow.take() {
+            Some(overflow) => Err(overflow),
+            None => Ok(()),
+        }
+    }
+
     // Halts on available output
     pub fn run_until_output(&mut self) -> Option<i64> {
         self.set_running();
@@ -97,19 +207,30 @@ impl IntCodeCpu {
     pub fn run_until_event(&mut self) -> Event {
         self.set_running();
         while self.running {
-            let curr_ip = self.ip;
-            let inst = self.fetch_and_decode();
-            if let Some(event) = self.execute(&inst, true) {
-                if event == Event::InputRequired {
-                    self.ip = curr_ip;
-                }
+            if let Some(event) = self.step_event() {
                 return event;
             }
         }
         Event::Halted
     }
 
+    // Executes a single instruction, ip stays on IN if no input is available
+    fn step_event(&mut self) -> Option<Event> {
+        let curr_ip = self.ip;
+        let inst = self.fetch_and_decode();
+        let event = self.execute(&inst, true);
+        if event == Some(Event::InputRequired) {
+            self.ip = curr_ip;
+        }
+        event
+    }
+
     fn fetch(&mut self, addr: usize) -> i64 {
+        if !self.peripherals.is_empty() {
+            if let Some(device) = self.peripherals.find(addr) {
+                return device.lock().unwrap().read(addr);
+            }
+        }
         if addr >= self.memory.len() {
             self.memory.resize(addr + 1, 0);
         }
@@ -117,6 +238,12 @@ impl IntCodeCpu {
     }
 
     fn store(&mut self, addr: usize, val: i64) {
+        if !self.peripherals.is_empty() {
+            if let Some(device) = self.peripherals.find(addr) {
+                device.lock().unwrap().write(addr, val);
+                return;
+            }
+        }
         if addr >= self.memory.len() {
             self.memory.resize(addr + 1, 0);
         }
@@ -203,13 +330,36 @@ impl IntCodeCpu {
     }
 
     fn execute(&mut self, inst: &Instruction, wait_for_input: bool) -> Option<Event> {
+        let blocked = wait_for_input && self.input.is_empty();
+        if !(blocked && matches!(inst, Instruction::IN { .. })) {
+            self.steps += 1;
+            if let Some(coverage) = &mut self.coverage {
+                coverage.record(self.ip);
+            }
+        }
         match inst {
             Instruction::ADD { src1, src2, dst } => {
-                self.store(*dst as usize, src1 + src2);
+                let result = match self.arithmetic {
+                    Arithmetic::Wrap => Some(src1.wrapping_add(*src2)),
+                    Arithmetic::Saturate => Some(src1.saturating_add(*src2)),
+                    Arithmetic::Trap => src1.checked_add(*src2),
+                };
+                match result {
+                    Some(val) => self.store(*dst as usize, val),
+                    None => return Some(self.trap("ADD", *src1, *src2)),
+                }
                 self.ip += 4;
             }
             Instruction::MUL { src1, src2, dst } => {
-                self.store(*dst as usize, src1 * src2);
+                let result = match self.arithmetic {
+                    Arithmetic::Wrap => Some(src1.wrapping_mul(*src2)),
+                    Arithmetic::Saturate => Some(src1.saturating_mul(*src2)),
+                    Arithmetic::Trap => src1.checked_mul(*src2),
+                };
+                match result {
+                    Some(val) => self.store(*dst as usize, val),
+                    None => return Some(self.trap("MUL", *src1, *src2)),
+                }
                 self.ip += 4;
             }
             Instruction::IN { dst } => {
@@ -226,6 +376,7 @@ impl IntCodeCpu {
                 return Some(Event::OutputAvailable(*src));
             }
             Instruction::JNZ { cond, target } => {
+                self.cover_branch(*cond != 0);
                 if *cond != 0 {
                     self.ip = *target as usize;
                 } else {
@@ -233,6 +384,7 @@ impl IntCodeCpu {
                 }
             }
             Instruction::JZ { cond, target } => {
+                self.cover_branch(*cond == 0);
                 if *cond == 0 {
                     self.ip = *target as usize;
                 } else {
@@ -258,6 +410,24 @@ impl IntCodeCpu {
         None
     }
 
+    fn cover_branch(&mut self, taken: bool) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_branch(self.ip, taken);
+        }
+    }
+
+    // Halts the cpu without advancing ip, so the overflowing instruction can be inspected
+    fn trap(&mut self, opcode: &'static str, src1: i64, src2: i64) -> Event {
+        self.overflow = Some(Overflow {
+            ip: self.ip,
+            opcode,
+            src1,
+            src2,
+        });
+        self.halt();
+        Event::Halted
+    }
+
     fn step(&mut self) {
         let inst = self.fetch_and_decode();
         self.execute(&inst, false);
@@ -354,4 +524,44 @@ mod tests {
         cpu.run();
         assert_eq!(cpu.output.pop_front(), Some(1_125_899_906_842_624));
     }
+
+    #[test]
+    fn test_overflow() {
+        let code = "1102,4294967296,4294967296,7,4,7,99,0";
+        let mut cpu = IntCodeCpu::from_code(code);
+        cpu.run();
+        assert_eq!(cpu.output.pop_front(), Some(0));
+
+        cpu = IntCodeCpu::from_code(code);
+        cpu.set_arithmetic(Arithmetic::Saturate);
+        cpu.run();
+        assert_eq!(cpu.output.pop_front(), Some(i64::MAX));
+
+        cpu = IntCodeCpu::from_code(code);
+        cpu.set_arithmetic(Arithmetic::Trap);
+        assert_eq!(
+            cpu.try_run(),
+            Err(Overflow {
+                ip: 0,
+                opcode: "MUL",
+                src1: 4_294_967_296,
+                src2: 4_294_967_296
+            })
+        );
+        assert!(cpu.output.is_empty());
+    }
+
+    #[test]
+    fn test_overflow_event() {
+        let mut cpu = IntCodeCpu::from_code("104,1,1101,9223372036854775807,1,0,99");
+        cpu.set_arithmetic(Arithmetic::Trap);
+        assert_eq!(cpu.run_until_event(), Event::OutputAvailable(1));
+        assert_eq!(cpu.run_until_event(), Event::Halted);
+        assert_eq!(cpu.overflow().map(|o| o.ip), Some(2));
+
+        cpu = IntCodeCpu::from_code("1102,34915192,34915192,7,4,7,99,0");
+        cpu.set_arithmetic(Arithmetic::Trap);
+        assert_eq!(cpu.try_run(), Ok(()));
+        assert_eq!(cpu.output.pop_front(), Some(34_915_192 * 34_915_192));
+    }
 }
@@ -0,0 +1,393 @@
+use super::{Event, IntCodeCpu};
+use itertools::Itertools;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::thread;
+
+// Assignments a search thread takes from the queue at a time
+const CHUNK: usize = 64;
+
+// How amplifiers are connected. Written like "A-A-(A|A-A)-A": A is an amplifier, - connects
+// stages in series and (..|..) branches that all get the same signal and merge by taking the
+// largest output. A plain number n is a chain of n amplifiers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stage {
+    Amp,
+    Series(Vec<Stage>),
+    Branch(Vec<Stage>),
+}
+
+impl Stage {
+    pub fn chain(amps: usize) -> Stage {
+        Stage::Series(vec![Stage::Amp; amps])
+    }
+
+    pub fn amps(&self) -> usize {
+        match self {
+            Stage::Amp => 1,
+            Stage::Series(stages) | Stage::Branch(stages) => stages.iter().map(Stage::amps).sum(),
+        }
+    }
+
+    // Sends the signal through the stage, amps holds exactly the amplifiers of this stage in
+    // order. None if an amplifier halts or waits for input before producing an output.
+    fn pass(&self, amps: &mut [IntCodeCpu], signal: i64) -> Option<i64> {
+        match self {
+            Stage::Amp => {
+                let amp = &mut amps[0];
+                amp.input.push_back(signal);
+                match amp.run_until_event() {
+                    Event::OutputAvailable(out) => Some(out),
+                    Event::InputRequired | Event::Halted => None,
+                }
+            }
+            Stage::Series(stages) => {
+                let mut signal = signal;
+                let mut rest = amps;
+                for stage in stages {
+                    let (own, tail) = rest.split_at_mut(stage.amps());
+                    signal = stage.pass(own, signal)?;
+                    rest = tail;
+                }
+                Some(signal)
+            }
+            Stage::Branch(stages) => {
+                let mut best = None;
+                let mut rest = amps;
+                for stage in stages {
+                    let (own, tail) = rest.split_at_mut(stage.amps());
+                    best = best.max(Some(stage.pass(own, signal)?));
+                    rest = tail;
+                }
+                best
+            }
+        }
+    }
+
+    fn parse_series(chars: &[char], pos: &mut usize) -> Result<Stage, String> {
+        let mut stages = vec![Stage::parse_item(chars, pos)?];
+        while chars.get(*pos) == Some(&'-') {
+            *pos += 1;
+            stages.push(Stage::parse_item(chars, pos)?);
+        }
+        Ok(match stages.len() {
+            1 => stages.pop().unwrap(),
+            _ => Stage::Series(stages),
+        })
+    }
+
+    fn parse_item(chars: &[char], pos: &mut usize) -> Result<Stage, String> {
+        match chars.get(*pos) {
+            Some('A') => {
+                *pos += 1;
+                Ok(Stage::Amp)
+            }
+            Some('(') => {
+                *pos += 1;
+                let mut branches = vec![Stage::parse_series(chars, pos)?];
+                while chars.get(*pos) == Some(&'|') {
+                    *pos += 1;
+                    branches.push(Stage::parse_series(chars, pos)?);
+                }
+                if chars.get(*pos) != Some(&')') {
+                    return Err(format!("expected ) at position {}", *pos));
+                }
+                *pos += 1;
+                Ok(Stage::Branch(branches))
+            }
+            Some(c) => Err(format!("unexpected {:?} at position {}", c, *pos)),
+            None => Err("unexpected end of circuit".to_string()),
+        }
+    }
+}
+
+impl FromStr for Stage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Stage, String> {
+        if let Ok(amps) = s.parse::<usize>() {
+            return match amps {
+                0 => Err("a circuit needs at least one amplifier".to_string()),
+                _ => Ok(Stage::chain(amps)),
+            };
+        }
+        let chars: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut pos = 0;
+        let stage = Stage::parse_series(&chars, &mut pos)?;
+        match chars.get(pos) {
+            None => Ok(stage),
+            Some(c) => Err(format!("unexpected {:?} at position {}", c, pos)),
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stage::Amp => write!(f, "A"),
+            Stage::Series(stages) => {
+                write!(f, "{}", stages.iter().map(|s| s.to_string()).join("-"))
+            }
+            Stage::Branch(stages) => {
+                write!(f, "({})", stages.iter().map(|s| s.to_string()).join("|"))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wiring {
+    // The signal passes the circuit once
+    Series,
+    // The output is fed back into the circuit until an amplifier halts
+    Feedback,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PhaseError {
+    // The circuit has a different number of amplifiers
+    Count { expected: usize, found: usize },
+    // The phase setting is not in the circuit's phase set
+    Unknown(i64),
+}
+
+impl fmt::Display for PhaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PhaseError::Count { expected, found } => {
+                write!(f, "expected {} phase settings, got {}", expected, found)
+            }
+            PhaseError::Unknown(phase) => write!(f, "phase setting {} is not allowed", phase),
+        }
+    }
+}
+
+impl Error for PhaseError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Best {
+    // Phase settings of the amplifiers in circuit order
+    pub phases: Vec<i64>,
+    pub signal: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Circuit {
+    pub stages: Stage,
+    // Every amplifier gets a different phase setting from this set
+    pub phases: Vec<i64>,
+    pub wiring: Wiring,
+}
+
+impl Circuit {
+    pub fn new(stages: Stage, phases: Vec<i64>, wiring: Wiring) -> Circuit {
+        Circuit {
+            stages,
+            phases,
+            wiring,
+        }
+    }
+
+    // Signal sent to the thrusters for the given phase settings, starting with signal 0. In
+    // feedback wiring this is the last signal of the last complete pass. The settings must come
+    // from the circuit's phase set, one per amplifier.
+    pub fn run(&self, cpu: &IntCodeCpu, phases: &[i64]) -> Result<Option<i64>, PhaseError> {
+        if phases.len() != self.stages.amps() {
+            return Err(PhaseError::Count {
+                expected: self.stages.amps(),
+                found: phases.len(),
+            });
+        }
+        if let Some(phase) = phases.iter().find(|p| !self.phases.contains(p)) {
+            return Err(PhaseError::Unknown(*phase));
+        }
+        Ok(self.signal(cpu, phases))
+    }
+
+    // Like run without checking the settings, assignments are valid by construction
+    fn signal(&self, cpu: &IntCodeCpu, phases: &[i64]) -> Option<i64> {
+        let mut amps: Vec<IntCodeCpu> = phases
+            .iter()
+            .map(|phase| {
+                let mut amp = cpu.clone();
+                amp.input.push_back(*phase);
+                amp
+            })
+            .collect();
+        let mut signal = self.stages.pass(&mut amps, 0)?;
+        if self.wiring == Wiring::Feedback {
+            while let Some(next) = self.stages.pass(&mut amps, signal) {
+                signal = next;
+            }
+        }
+        Some(signal)
+    }
+
+    // All assignments of distinct phase settings to the amplifiers
+    pub fn assignments(&self) -> impl Iterator<Item = Vec<i64>> + '_ {
+        self.phases.iter().cloned().permutations(self.stages.amps())
+    }
+
+    // The phase settings giving the highest signal; ties go to the first assignment in
+    // permutation order. None if no assignment produces a signal.
+    pub fn search(&self, cpu: &IntCodeCpu) -> Option<Best> {
+        let mut best: Option<Best> = None;
+        for phases in self.assignments() {
+            if let Some(signal) = self.signal(cpu, &phases) {
+                if best.as_ref().map_or(true, |b| signal > b.signal) {
+                    best = Some(Best { phases, signal });
+                }
+            }
+        }
+        best
+    }
+
+    // The same result as search, with the assignments spread over the given number of threads.
+    // Threads take chunks from a shared queue and ties are broken by position in permutation
+    // order, so the result does not depend on scheduling.
+    pub fn search_parallel(&self, cpu: &IntCodeCpu, threads: usize) -> Option<Best> {
+        // permutations may start over when polled after the end, so the queue is fused
+        let queue = Mutex::new(self.assignments().enumerate().fuse());
+        let results: Vec<Option<(usize, Best)>> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut best: Option<(usize, Best)> = None;
+                        loop {
+                            let chunk: Vec<_> =
+                                queue.lock().unwrap().by_ref().take(CHUNK).collect();
+                            if chunk.is_empty() {
+                                return best;
+                            }
+                            for (index, phases) in chunk {
+                                if let Some(signal) = self.signal(cpu, &phases) {
+                                    if best.as_ref().map_or(true, |(_, b)| signal > b.signal) {
+                                        best = Some((index, Best { phases, signal }));
+                                    }
+                                }
+                            }
+                        }
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+        results
+            .into_iter()
+            .flatten()
+            .max_by(|(i, a), (j, b)| a.signal.cmp(&b.signal).then(j.cmp(i)))
+            .map(|(_, best)| best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERIES: &str = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0";
+    const FEEDBACK: &str = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,
+        27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+
+    #[test]
+    fn test_parse() {
+        let stage: Stage = "A-(A|A-A)-A".parse().unwrap();
+        assert_eq!(
+            stage,
+            Stage::Series(vec![
+                Stage::Amp,
+                Stage::Branch(vec![Stage::Amp, Stage::chain(2)]),
+                Stage::Amp,
+            ])
+        );
+        assert_eq!(stage.amps(), 5);
+        assert_eq!(stage.to_string(), "A-(A|A-A)-A");
+        assert_eq!("3".parse(), Ok(Stage::chain(3)));
+        assert_eq!("A".parse(), Ok(Stage::Amp));
+        assert!("A-(A|A".parse::<Stage>().is_err());
+        assert!("A-B".parse::<Stage>().is_err());
+        assert!("0".parse::<Stage>().is_err());
+    }
+
+    #[test]
+    fn test_series() {
+        let cpu = IntCodeCpu::from_code(SERIES);
+        let circuit = Circuit::new(Stage::chain(5), (0..5).collect(), Wiring::Series);
+        assert_eq!(
+            circuit.search(&cpu),
+            Some(Best {
+                phases: vec![4, 3, 2, 1, 0],
+                signal: 43210
+            })
+        );
+        assert_eq!(circuit.run(&cpu, &[0, 1, 2, 3, 4]), Ok(Some(1234)));
+        assert_eq!(
+            circuit.run(&cpu, &[0, 1, 2]),
+            Err(PhaseError::Count {
+                expected: 5,
+                found: 3
+            })
+        );
+        assert_eq!(
+            circuit.run(&cpu, &[0, 1, 2, 3, 10]),
+            Err(PhaseError::Unknown(10))
+        );
+    }
+
+    #[test]
+    fn test_feedback() {
+        let cpu = IntCodeCpu::from_code(FEEDBACK);
+        let circuit = Circuit::new(Stage::chain(5), (5..10).collect(), Wiring::Feedback);
+        assert_eq!(
+            circuit.search(&cpu),
+            Some(Best {
+                phases: vec![9, 8, 7, 6, 5],
+                signal: 139_629_729
+            })
+        );
+    }
+
+    #[test]
+    fn test_parallel() {
+        let cpu = IntCodeCpu::from_code(FEEDBACK);
+        let circuit = Circuit::new(Stage::chain(5), (5..10).collect(), Wiring::Feedback);
+        for threads in [0, 1, 4, 200] {
+            assert_eq!(circuit.search_parallel(&cpu, threads), circuit.search(&cpu));
+        }
+        // every assignment gives the same signal, the first one wins
+        let cpu = IntCodeCpu::from_code("3,9,3,9,4,9,99,0,0,0");
+        let circuit = Circuit::new(Stage::chain(4), (0..7).collect(), Wiring::Series);
+        let first = Best {
+            phases: vec![0, 1, 2, 3],
+            signal: 0,
+        };
+        assert_eq!(circuit.search(&cpu), Some(first.clone()));
+        assert_eq!(circuit.search_parallel(&cpu, 8), Some(first));
+    }
+
+    #[test]
+    fn test_branches() {
+        // each amplifier computes 10 * signal + phase
+        let cpu = IntCodeCpu::from_code(SERIES);
+        let circuit = Circuit::new(
+            "A-(A|A-A)".parse().unwrap(),
+            (1..5).collect(),
+            Wiring::Series,
+        );
+        assert_eq!(circuit.run(&cpu, &[1, 2, 3, 4]), Ok(Some(134)));
+        assert_eq!(circuit.run(&cpu, &[1, 4, 2, 3]), Ok(Some(123)));
+        assert_eq!(
+            circuit.search(&cpu),
+            Some(Best {
+                phases: vec![4, 1, 3, 2],
+                signal: 432
+            })
+        );
+        assert_eq!(circuit.search_parallel(&cpu, 3), circuit.search(&cpu));
+        // fewer phases than amplifiers leaves nothing to try
+        let circuit = Circuit::new(Stage::chain(3), vec![0, 1], Wiring::Series);
+        assert_eq!(circuit.search(&cpu), None);
+        assert_eq!(circuit.search_parallel(&cpu, 2), None);
+    }
+}
@@ -0,0 +1,251 @@
+use super::disasm::{Mode, Op, HLT};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Branch {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+// Execution counts per instruction address and taken/not taken counts per conditional jump,
+// recorded by an IntCodeCpu with coverage enabled
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Coverage {
+    hits: BTreeMap<usize, u64>,
+    branches: BTreeMap<usize, Branch>,
+}
+
+impl Coverage {
+    pub(crate) fn record(&mut self, addr: usize) {
+        *self.hits.entry(addr).or_insert(0) += 1;
+    }
+
+    pub(crate) fn record_branch(&mut self, addr: usize, taken: bool) {
+        let branch = self.branches.entry(addr).or_default();
+        if taken {
+            branch.taken += 1;
+        } else {
+            branch.not_taken += 1;
+        }
+    }
+
+    pub fn hits(&self, addr: usize) -> u64 {
+        self.hits.get(&addr).copied().unwrap_or(0)
+    }
+
+    pub fn executed(&self) -> impl Iterator<Item = usize> + '_ {
+        self.hits.keys().copied()
+    }
+
+    pub fn branch(&self, addr: usize) -> Option<Branch> {
+        self.branches.get(&addr).copied()
+    }
+
+    pub fn merge(&mut self, other: &Coverage) {
+        for (addr, hits) in &other.hits {
+            *self.hits.entry(*addr).or_insert(0) += hits;
+        }
+        for (addr, branch) in &other.branches {
+            let merged = self.branches.entry(*addr).or_default();
+            merged.taken += branch.taken;
+            merged.not_taken += branch.not_taken;
+        }
+    }
+
+    // Finds instructions that were executed or can be reached from an executed instruction by
+    // falling through or by a jump with an immediate target. Code that is only reachable by
+    // computed jumps and never ran cannot be told apart from data.
+    fn instructions(&self, program: &[i64]) -> BTreeMap<usize, Op> {
+        let mut ops = BTreeMap::new();
+        let mut to_visit: Vec<usize> = self.executed().chain(Some(0)).collect();
+        while let Some(addr) = to_visit.pop() {
+            if ops.contains_key(&addr) {
+                continue;
+            }
+            let op = match Op::decode(program, addr) {
+                Some(op) => op,
+                None => continue,
+            };
+            if op.is_jump() {
+                if let (Mode::Immediate, target) = op.params[1] {
+                    if target >= 0 && op.branch() != Some(false) {
+                        to_visit.push(target as usize);
+                    }
+                }
+            }
+            if op.opcode != HLT && !(op.is_jump() && op.branch() == Some(true)) {
+                to_visit.push(op.cells().end);
+            }
+            ops.insert(addr, op);
+        }
+        ops
+    }
+
+    // Writes a disassembly of the program annotated with execution counts. Instructions that
+    // never ran are marked with #####, data cells are listed in groups. Instructions that were
+    // rewritten by the program before they ran cannot be decoded from the image and are only
+    // listed with their execution count.
+    pub fn write_listing<W: Write>(&self, program: &[i64], out: &mut W) -> io::Result<()> {
+        let ops = self.instructions(program);
+        let starts: BTreeSet<usize> = ops.keys().copied().chain(self.executed()).collect();
+        let mut addr = 0;
+        while addr < program.len() {
+            if let Some(op) = ops.get(&addr) {
+                let hits = match self.hits(addr) {
+                    0 => "#####".to_string(),
+                    hits => hits.to_string(),
+                };
+                write!(out, "{:>10} | {:>6}: {}", hits, addr, op)?;
+                if op.is_jump() {
+                    let branch = self.branch(addr).unwrap_or_default();
+                    write!(
+                        out,
+                        "    [taken {}, not taken {}]",
+                        branch.taken, branch.not_taken
+                    )?;
+                }
+                writeln!(out)?;
+                addr = op.cells().end;
+            } else if self.hits(addr) > 0 {
+                // the program patched this instruction before running it
+                writeln!(
+                    out,
+                    "{:>10} | {:>6}: modified at run time, initially {}",
+                    self.hits(addr),
+                    addr,
+                    program[addr]
+                )?;
+                addr += 1;
+            } else {
+                let end = starts
+                    .range(addr + 1..)
+                    .next()
+                    .copied()
+                    .unwrap_or(program.len())
+                    .min(addr + 8);
+                let data: Vec<_> = program[addr..end].iter().map(|v| v.to_string()).collect();
+                writeln!(out, "{:>10} | {:>6}: data {}", "", addr, data.join(","))?;
+                addr = end;
+            }
+        }
+        Ok(())
+    }
+
+    // Writes an lcov tracefile for the program. lcov counts lines from 1, so every address is
+    // reported as line address + 1.
+    pub fn write_lcov<W: Write>(&self, program: &[i64], name: &str, out: &mut W) -> io::Result<()> {
+        let ops = self.instructions(program);
+        writeln!(out, "TN:")?;
+        writeln!(out, "SF:{}", name)?;
+        let mut branches_found = 0;
+        let mut branches_hit = 0;
+        for op in ops.values().filter(|op| op.is_jump()) {
+            let line = op.addr + 1;
+            branches_found += 2;
+            match self.branch(op.addr) {
+                Some(branch) => {
+                    writeln!(out, "BRDA:{},0,0,{}", line, branch.taken)?;
+                    writeln!(out, "BRDA:{},0,1,{}", line, branch.not_taken)?;
+                    branches_hit += (branch.taken > 0) as usize + (branch.not_taken > 0) as usize;
+                }
+                None => {
+                    writeln!(out, "BRDA:{},0,0,-", line)?;
+                    writeln!(out, "BRDA:{},0,1,-", line)?;
+                }
+            }
+        }
+        writeln!(out, "BRF:{}", branches_found)?;
+        writeln!(out, "BRH:{}", branches_hit)?;
+        for addr in ops.keys() {
+            writeln!(out, "DA:{},{}", addr + 1, self.hits(*addr))?;
+        }
+        writeln!(out, "LF:{}", ops.len())?;
+        writeln!(
+            out,
+            "LH:{}",
+            ops.keys().filter(|addr| self.hits(**addr) > 0).count()
+        )?;
+        writeln!(out, "end_of_record")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::IntCodeCpu;
+
+    // outputs 1 if the input equals 8, else 0
+    const CODE: &str = "3,9,8,9,10,9,4,9,99,-1,8";
+    // outputs 0 if the input is zero, else 1
+    const JUMP_CODE: &str = "3,13,1006,13,10,104,1,1105,1,12,104,0,99,0";
+
+    fn run(code: &str, input: i64) -> IntCodeCpu {
+        let mut cpu = IntCodeCpu::from_code(code);
+        cpu.enable_coverage();
+        cpu.input.push_back(input);
+        cpu.run();
+        cpu
+    }
+
+    #[test]
+    fn test_record() {
+        let cpu = run(CODE, 8);
+        let coverage = cpu.coverage().unwrap();
+        assert_eq!(coverage.executed().collect::<Vec<_>>(), vec![0, 2, 6, 8]);
+        assert_eq!(coverage.hits(2), 1);
+    }
+
+    #[test]
+    fn test_branches() {
+        let mut coverage = run(JUMP_CODE, 0).take_coverage().unwrap();
+        let branch = coverage.branch(2).unwrap();
+        assert_eq!((branch.taken, branch.not_taken), (1, 0));
+        assert_eq!(coverage.hits(5), 0);
+
+        coverage.merge(&run(JUMP_CODE, 5).take_coverage().unwrap());
+        coverage.merge(&run(JUMP_CODE, 3).take_coverage().unwrap());
+        let branch = coverage.branch(2).unwrap();
+        assert_eq!((branch.taken, branch.not_taken), (1, 2));
+        assert_eq!(coverage.hits(0), 3);
+        assert_eq!(coverage.hits(5), 2);
+    }
+
+    #[test]
+    fn test_input_required_is_not_counted() {
+        let mut cpu = IntCodeCpu::from_code(CODE);
+        cpu.enable_coverage();
+        cpu.run_until_event();
+        cpu.run_until_event();
+        assert_eq!(cpu.coverage().unwrap().hits(0), 0);
+        cpu.input.push_back(1);
+        cpu.run_until_event();
+        assert_eq!(cpu.coverage().unwrap().hits(0), 1);
+    }
+
+    #[test]
+    fn test_reports() {
+        let program = IntCodeCpu::from_code(JUMP_CODE).memory().to_vec();
+        let coverage = run(JUMP_CODE, 0).take_coverage().unwrap();
+
+        let mut listing = vec![];
+        coverage.write_listing(&program, &mut listing).unwrap();
+        assert_eq!(
+            String::from_utf8(listing).unwrap(),
+            "         1 |      0: IN -> [13]
+         1 |      2: JZ [13], #10    [taken 1, not taken 0]
+     ##### |      5: OUT #1
+     ##### |      7: JNZ #1, #12    [taken 0, not taken 0]
+         1 |     10: OUT #0
+         1 |     12: HLT
+           |     13: data 0
+"
+        );
+
+        let mut lcov = vec![];
+        coverage.write_lcov(&program, "jump", &mut lcov).unwrap();
+        let lcov = String::from_utf8(lcov).unwrap();
+        assert!(lcov.contains("BRDA:3,0,0,1\nBRDA:3,0,1,0\nBRDA:8,0,0,-\n"));
+        assert!(lcov.contains("BRF:4\nBRH:1\n"));
+        assert!(lcov.contains("DA:6,0\n"));
+    }
+}
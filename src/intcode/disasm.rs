@@ -0,0 +1,175 @@
+use std::fmt;
+
+// Static decoding of Intcode instructions, shared by the tools that inspect programs without
+// running them. Unknown parameter mode digits decode as position mode, like in the cpu.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl Mode {
+    pub(crate) fn from_digit(digit: i64) -> Mode {
+        match digit {
+            1 => Mode::Immediate,
+            2 => Mode::Relative,
+            _ => Mode::Position,
+        }
+    }
+
+    pub(crate) fn digit(self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Op {
+    pub(crate) addr: usize,
+    pub(crate) opcode: i64,
+    pub(crate) params: Vec<(Mode, i64)>,
+}
+
+pub(crate) const ADD: i64 = 1;
+pub(crate) const MUL: i64 = 2;
+pub(crate) const IN: i64 = 3;
+pub(crate) const OUT: i64 = 4;
+pub(crate) const JNZ: i64 = 5;
+pub(crate) const JZ: i64 = 6;
+pub(crate) const LT: i64 = 7;
+pub(crate) const EQ: i64 = 8;
+pub(crate) const RBO: i64 = 9;
+pub(crate) const HLT: i64 = 99;
+pub(crate) const OPCODES: [i64; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+
+impl Op {
+    pub(crate) fn decode(program: &[i64], addr: usize) -> Option<Op> {
+        let inst = *program.get(addr)?;
+        let opcode = inst % 100;
+        let num_params = match opcode {
+            1 | 2 | 7 | 8 => 3,
+            3 | 4 | 9 => 1,
+            5 | 6 => 2,
+            99 => 0,
+            _ => return None,
+        };
+        let mut params = Vec::with_capacity(num_params);
+        let mut modes = inst / 100;
+        for i in 0..num_params {
+            params.push((Mode::from_digit(modes % 10), *program.get(addr + 1 + i)?));
+            modes /= 10;
+        }
+        Some(Op {
+            addr,
+            opcode,
+            params,
+        })
+    }
+
+    pub(crate) fn encode(&self) -> Vec<i64> {
+        let modes = self
+            .params
+            .iter()
+            .rev()
+            .fold(0, |acc, (mode, _)| acc * 10 + mode.digit());
+        let mut cells = vec![modes * 100 + self.opcode];
+        cells.extend(self.params.iter().map(|(_, val)| *val));
+        cells
+    }
+
+    pub(crate) fn cells(&self) -> std::ops::Range<usize> {
+        self.addr..self.addr + self.params.len() + 1
+    }
+
+    pub(crate) fn dst_index(&self) -> Option<usize> {
+        match self.opcode {
+            ADD | MUL | LT | EQ => Some(2),
+            IN => Some(0),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_jump(&self) -> bool {
+        self.opcode == JNZ || self.opcode == JZ
+    }
+
+    // Returns Some(true) if the jump is always taken, Some(false) if it is never taken
+    pub(crate) fn branch(&self) -> Option<bool> {
+        match self.params[0] {
+            (Mode::Immediate, cond) if self.opcode == JNZ => Some(cond != 0),
+            (Mode::Immediate, cond) if self.opcode == JZ => Some(cond == 0),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn mnemonic(opcode: i64) -> &'static str {
+    match opcode {
+        1 => "ADD",
+        2 => "MUL",
+        3 => "IN",
+        4 => "OUT",
+        5 => "JNZ",
+        6 => "JZ",
+        7 => "LT",
+        8 => "EQ",
+        9 => "RBO",
+        99 => "HLT",
+        _ => "???",
+    }
+}
+
+fn operand(mode: Mode, val: i64) -> String {
+    match mode {
+        Mode::Position => format!("[{}]", val),
+        Mode::Immediate => format!("#{}", val),
+        Mode::Relative if val < 0 => format!("[rbp-{}]", -val),
+        Mode::Relative => format!("[rbp+{}]", val),
+    }
+}
+
+// Formats instructions like `ADD [20], #0 -> [rbp+1]`
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", mnemonic(self.opcode))?;
+        let dst = self.dst_index();
+        let srcs: Vec<_> = self
+            .params
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != dst)
+            .map(|(_, (mode, val))| operand(*mode, *val))
+            .collect();
+        if !srcs.is_empty() {
+            write!(f, " {}", srcs.join(", "))?;
+        }
+        if let Some(i) = dst {
+            let (mode, val) = self.params[i];
+            write!(f, " -> {}", operand(mode, val))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let program = [1001, 20, 0, 21, 203, -3, 106, 0, 7, 99];
+        let ops: Vec<_> = [0, 4, 6, 9]
+            .iter()
+            .map(|addr| Op::decode(&program, *addr).unwrap().to_string())
+            .collect();
+        assert_eq!(
+            ops,
+            vec!["ADD [20], #0 -> [21]", "IN -> [rbp-3]", "JZ #0, [7]", "HLT"]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use super::{Event, IntCodeCpu};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    BreadthFirst,
+    DepthFirst,
+    // Shortest paths by action cost
+    Dijkstra,
+}
+
+// A sequence of inputs fed to the cpu in one move
+#[derive(Clone, Debug, PartialEq)]
+pub struct Action {
+    pub input: Vec<i64>,
+    pub cost: u64,
+}
+
+impl Action {
+    pub fn new(input: Vec<i64>) -> Action {
+        Action { input, cost: 1 }
+    }
+
+    pub fn with_cost(input: Vec<i64>, cost: u64) -> Action {
+        Action { input, cost }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Node<K> {
+    pub distance: u64,
+    // The previous state and the index of the action that led here
+    pub parent: Option<(K, usize)>,
+    pub cpu: IntCodeCpu,
+}
+
+// The reachable states with the cpu in that state. With DepthFirst the distances are those of
+// the search tree and not necessarily the shortest ones.
+#[derive(Clone, Debug)]
+pub struct Explored<K> {
+    pub start: K,
+    pub nodes: HashMap<K, Node<K>>,
+}
+
+impl<K: Clone + Eq + Hash> Explored<K> {
+    pub fn distance(&self, key: &K) -> Option<u64> {
+        self.nodes.get(key).map(|node| node.distance)
+    }
+
+    pub fn cpu(&self, key: &K) -> Option<&IntCodeCpu> {
+        self.nodes.get(key).map(|node| &node.cpu)
+    }
+
+    pub fn max_distance(&self) -> u64 {
+        self.nodes
+            .values()
+            .map(|node| node.distance)
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Indices of the actions leading from the start to the state
+    pub fn path(&self, key: &K) -> Option<Vec<usize>> {
+        let mut path = vec![];
+        let mut node = self.nodes.get(key)?;
+        while let Some((parent, action)) = &node.parent {
+            path.push(*action);
+            node = &self.nodes[parent];
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+// Feeds the action to a copy of the cpu and runs it until it asks for more input or halts
+fn apply(cpu: &IntCodeCpu, action: &Action) -> (IntCodeCpu, Vec<i64>) {
+    let mut cpu = cpu.clone();
+    cpu.input.extend(&action.input);
+    while let Event::OutputAvailable(_) = cpu.run_until_event() {}
+    let outputs = cpu.output.drain(..).collect();
+    (cpu, outputs)
+}
+
+// Explores the states reachable from start by applying the actions. The transition function
+// gets the current state, the action and the outputs it produced and returns the new state, or
+// None if the move is not possible. States are identified by their key only, so the cpu of the
+// first visit is kept.
+pub fn explore<K, F>(
+    cpu: IntCodeCpu,
+    start: K,
+    actions: &[Action],
+    strategy: Strategy,
+    mut transition: F,
+) -> Explored<K>
+where
+    K: Clone + Eq + Hash,
+    F: FnMut(&K, &Action, &[i64]) -> Option<K>,
+{
+    let mut nodes = HashMap::new();
+    match strategy {
+        Strategy::BreadthFirst | Strategy::DepthFirst => {
+            let mut frontier = VecDeque::new();
+            nodes.insert(
+                start.clone(),
+                Node {
+                    distance: 0,
+                    parent: None,
+                    cpu,
+                },
+            );
+            frontier.push_back(start.clone());
+            loop {
+                let key = match strategy {
+                    Strategy::DepthFirst => frontier.pop_back(),
+                    _ => frontier.pop_front(),
+                };
+                let key = match key {
+                    Some(key) => key,
+                    None => break,
+                };
+                let (distance, cpu) = {
+                    let node: &Node<K> = &nodes[&key];
+                    (node.distance, node.cpu.clone())
+                };
+                for (i, action) in actions.iter().enumerate() {
+                    let (next_cpu, outputs) = apply(&cpu, action);
+                    if let Some(next) = transition(&key, action, &outputs) {
+                        if nodes.contains_key(&next) {
+                            continue;
+                        }
+                        nodes.insert(
+                            next.clone(),
+                            Node {
+                                distance: distance + action.cost,
+                                parent: Some((key.clone(), i)),
+                                cpu: next_cpu,
+                            },
+                        );
+                        frontier.push_back(next);
+                    }
+                }
+            }
+        }
+        Strategy::Dijkstra => {
+            // the heap orders by distance, ties by insertion; candidates are kept aside so
+            // that neither states nor cpus need to be ordered
+            let mut candidates = vec![Some((start.clone(), None, cpu))];
+            let mut heap = BinaryHeap::new();
+            heap.push(Reverse((0, 0)));
+            while let Some(Reverse((distance, id))) = heap.pop() {
+                let (key, parent, cpu) = candidates[id].take().unwrap();
+                if nodes.contains_key(&key) {
+                    continue;
+                }
+                for (i, action) in actions.iter().enumerate() {
+                    let (next_cpu, outputs) = apply(&cpu, action);
+                    if let Some(next) = transition(&key, action, &outputs) {
+                        if !nodes.contains_key(&next) {
+                            heap.push(Reverse((distance + action.cost, candidates.len())));
+                            candidates.push(Some((next, Some((key.clone(), i)), next_cpu)));
+                        }
+                    }
+                }
+                nodes.insert(
+                    key,
+                    Node {
+                        distance,
+                        parent,
+                        cpu,
+                    },
+                );
+            }
+        }
+    }
+    Explored { start, nodes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // adds every input to an accumulator and outputs it
+    const CODE: &str = "3,13,1,14,13,14,4,14,1105,1,0,99,0,0,0";
+
+    fn counter(strategy: Strategy, actions: &[Action]) -> Explored<i64> {
+        let cpu = IntCodeCpu::from_code(CODE);
+        explore(cpu, 0, actions, strategy, |_, _, outputs| {
+            outputs.last().copied().filter(|val| *val <= 10)
+        })
+    }
+
+    #[test]
+    fn test_breadth_first() {
+        let explored = counter(
+            Strategy::BreadthFirst,
+            &[Action::new(vec![1]), Action::new(vec![3])],
+        );
+        assert_eq!(explored.nodes.len(), 11);
+        assert_eq!(explored.distance(&10), Some(4));
+        assert_eq!(explored.distance(&6), Some(2));
+        assert_eq!(explored.path(&7), Some(vec![0, 1, 1]));
+        assert_eq!(explored.cpu(&7).unwrap().memory()[14], 7);
+        assert_eq!(explored.max_distance(), 4);
+    }
+
+    #[test]
+    fn test_depth_first() {
+        let explored = counter(
+            Strategy::DepthFirst,
+            &[Action::new(vec![1]), Action::new(vec![3])],
+        );
+        assert_eq!(explored.nodes.len(), 11);
+        assert!(explored.distance(&10).unwrap() >= 4);
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let actions = [Action::new(vec![1]), Action::with_cost(vec![3], 5)];
+        let explored = counter(Strategy::Dijkstra, &actions);
+        assert_eq!(explored.distance(&6), Some(6));
+        assert_eq!(explored.distance(&10), Some(10));
+        let explored = counter(Strategy::Dijkstra, &[Action::with_cost(vec![1, 1], 1)]);
+        assert_eq!(explored.distance(&10), Some(5));
+        assert_eq!(explored.distance(&9), None);
+    }
+}
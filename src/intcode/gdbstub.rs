@@ -0,0 +1,406 @@
+use super::disasm::{Mode, Op, OPCODES};
+use super::{Event, IntCodeCpu};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+// Serves a subset of the GDB remote serial protocol for a single IntCodeCpu.
+//
+// There are two 64 bit little-endian registers, ip (0) and rbp (1). Memory is byte addressed:
+// cell n occupies bytes 8n..8n+8 in little-endian order, so ip, memory accesses and breakpoints
+// use cell addresses times 8. Code addresses that are not multiples of 8 are rejected. rbp is
+// a cell offset and stays in cells. Supported packets are ?, g, G, p, P, m, M, s, c, Z0/z0, D and k.
+// Outputs of the program are forwarded to the debugger console as O packets.
+
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGFPE: u8 = 8;
+const SIGSEGV: u8 = 11;
+
+// Memory accesses must end below this byte address or the end of the cpu's memory, whichever
+// is larger, so that a single M packet can not grow memory without bound
+const MEMORY_LIMIT: usize = 1 << 24;
+// Longest memory read answered at once, a debugger asks again for the rest
+const MAX_READ: usize = 0x1000;
+
+// Continuing checks for an interrupt from the debugger every this many instructions
+const INTERRUPT_INTERVAL: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stop {
+    Signal(u8),
+    Exited,
+}
+
+impl Stop {
+    fn reply(self) -> String {
+        match self {
+            Stop::Signal(signal) => format!("S{:02x}", signal),
+            Stop::Exited => "W00".to_string(),
+        }
+    }
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.reader.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    // Returns the next packet, or None if the debugger closed the connection
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(b'$') => {}
+                // acks and interrupts outside of continue
+                Some(_) => continue,
+            }
+            let mut data = vec![];
+            self.reader.read_until(b'#', &mut data)?;
+            data.pop();
+            let mut checksum = [0; 2];
+            self.reader.read_exact(&mut checksum)?;
+            let expected = u8::from_str_radix(&String::from_utf8_lossy(&checksum), 16).ok();
+            if expected == Some(checksum_of(&data)) {
+                self.writer.write_all(b"+")?;
+                return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+            }
+            self.writer.write_all(b"-")?;
+        }
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+        loop {
+            self.writer.write_all(packet.as_bytes())?;
+            match self.read_byte()? {
+                Some(b'-') => continue,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn interrupted(&mut self) -> io::Result<bool> {
+        if self.reader.buffer().is_empty() {
+            self.writer.set_nonblocking(true)?;
+            let filled = self.reader.fill_buf().map(|buf| buf.len());
+            self.writer.set_nonblocking(false)?;
+            match filled {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(e) => return Err(e),
+                Ok(_) => {}
+            }
+        }
+        if self.reader.buffer().first() == Some(&0x03) {
+            self.reader.consume(1);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn parse_addr_len(args: &str) -> Option<(usize, usize)> {
+    let mut parts = args.splitn(2, ',');
+    let addr = usize::from_str_radix(parts.next()?, 16).ok()?;
+    let len = usize::from_str_radix(parts.next()?, 16).ok()?;
+    Some((addr, len))
+}
+
+// The cell at a hex byte address, which has to be the start of the cell
+fn code_addr(hex: &str) -> Option<usize> {
+    let addr = usize::from_str_radix(hex, 16).ok()?;
+    match addr % 8 == 0 {
+        true => Some(addr / 8),
+        false => None,
+    }
+}
+
+pub struct GdbStub {
+    cpu: IntCodeCpu,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl GdbStub {
+    pub fn new(mut cpu: IntCodeCpu) -> GdbStub {
+        cpu.set_running();
+        GdbStub {
+            cpu,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    pub fn cpu(&self) -> &IntCodeCpu {
+        &self.cpu
+    }
+
+    pub fn into_cpu(self) -> IntCodeCpu {
+        self.cpu
+    }
+
+    // Handles a debugger session until it detaches, kills the program or disconnects
+    pub fn serve(&mut self, stream: TcpStream) -> io::Result<()> {
+        let mut conn = Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+        while let Some(packet) = conn.read_packet()? {
+            match self.handle(&mut conn, &packet)? {
+                Some(reply) => conn.send(&reply)?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the reply to the packet, or None to end the session
+    fn handle(&mut self, conn: &mut Connection, packet: &str) -> io::Result<Option<String>> {
+        let (command, args) = packet.split_at(packet.len().min(1));
+        let reply = match command {
+            "?" => self.stop_state().reply(),
+            "g" => to_hex(&[self.register(0), self.register(1)].concat()),
+            "G" => match from_hex(args) {
+                Some(ref bytes) if bytes.len() == 16 && self.set_register(0, &bytes[..8]) => {
+                    self.set_register(1, &bytes[8..]);
+                    "OK".to_string()
+                }
+                _ => "E01".to_string(),
+            },
+            "p" => match usize::from_str_radix(args, 16) {
+                Ok(reg) if reg < 2 => to_hex(&self.register(reg)),
+                _ => "E01".to_string(),
+            },
+            "P" => {
+                let mut parts = args.splitn(2, '=');
+                let reg = parts.next().and_then(|r| usize::from_str_radix(r, 16).ok());
+                match (reg, parts.next().and_then(from_hex)) {
+                    (Some(reg), Some(ref bytes))
+                        if reg < 2 && bytes.len() == 8 && self.set_register(reg, bytes) =>
+                    {
+                        "OK".to_string()
+                    }
+                    _ => "E01".to_string(),
+                }
+            }
+            "m" => match parse_addr_len(args).and_then(|(addr, len)| self.read_memory(addr, len)) {
+                Some(bytes) => to_hex(&bytes),
+                None => "E01".to_string(),
+            },
+            "M" => {
+                let mut parts = args.splitn(2, ':');
+                let range = parts.next().and_then(parse_addr_len);
+                match (range, parts.next().and_then(from_hex)) {
+                    (Some((addr, len)), Some(ref bytes))
+                        if bytes.len() == len && self.write_memory(addr, bytes) =>
+                    {
+                        "OK".to_string()
+                    }
+                    _ => "E01".to_string(),
+                }
+            }
+            "s" | "c" => {
+                if !args.is_empty() {
+                    match code_addr(args) {
+                        Some(ip) => self.cpu.ip = ip,
+                        None => return Ok(Some("E01".to_string())),
+                    }
+                }
+                let stop = if command == "s" {
+                    self.step().err().unwrap_or(Stop::Signal(SIGTRAP))
+                } else {
+                    self.resume(conn)?
+                };
+                self.forward_output(conn)?;
+                stop.reply()
+            }
+            "Z" | "z" => {
+                let mut parts = args.splitn(3, ',');
+                let kind = parts.next();
+                let addr = parts.next().map(code_addr);
+                match (kind, addr) {
+                    (Some("0"), Some(None)) | (Some("1"), Some(None)) => "E01".to_string(),
+                    (Some("0"), Some(Some(ip))) | (Some("1"), Some(Some(ip))) => {
+                        if command == "Z" {
+                            self.breakpoints.insert(ip);
+                        } else {
+                            self.breakpoints.remove(&ip);
+                        }
+                        "OK".to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            "H" => "OK".to_string(),
+            "q" if args.starts_with("Supported") => "PacketSize=1000".to_string(),
+            "q" if args == "Attached" => "1".to_string(),
+            "D" => {
+                conn.send("OK")?;
+                return Ok(None);
+            }
+            "k" => return Ok(None),
+            _ => String::new(),
+        };
+        Ok(Some(reply))
+    }
+
+    fn stop_state(&self) -> Stop {
+        if self.cpu.running() {
+            Stop::Signal(SIGTRAP)
+        } else {
+            Stop::Exited
+        }
+    }
+
+    fn register(&self, reg: usize) -> [u8; 8] {
+        let val = if reg == 0 {
+            (self.cpu.ip as u64).wrapping_mul(8)
+        } else {
+            self.cpu.rbp as u64
+        };
+        val.to_le_bytes()
+    }
+
+    // Returns false and leaves the register alone if ip is set to an address within a cell
+    fn set_register(&mut self, reg: usize, bytes: &[u8]) -> bool {
+        let mut val = [0; 8];
+        val.copy_from_slice(bytes);
+        let val = u64::from_le_bytes(val) as usize;
+        if reg == 0 {
+            if val % 8 != 0 {
+                return false;
+            }
+            self.cpu.ip = val / 8;
+        } else {
+            self.cpu.rbp = val;
+        }
+        true
+    }
+
+    // Memory is accessed directly, bypassing peripherals
+    // Whether the bytes addr..addr + len may be accessed
+    fn in_range(&self, addr: usize, len: usize) -> bool {
+        let limit = MEMORY_LIMIT.max(self.cpu.memory.len().saturating_mul(8));
+        matches!(addr.checked_add(len), Some(end) if end <= limit)
+    }
+
+    // The bytes at addr, at most MAX_READ of them. None if the range is out of bounds.
+    fn read_memory(&self, addr: usize, len: usize) -> Option<Vec<u8>> {
+        let len = len.min(MAX_READ);
+        if !self.in_range(addr, len) {
+            return None;
+        }
+        let bytes = (addr..addr + len)
+            .map(|b| {
+                let cell = self.cpu.memory.get(b / 8).copied().unwrap_or(0);
+                cell.to_le_bytes()[b % 8]
+            })
+            .collect();
+        Some(bytes)
+    }
+
+    // Writes the bytes at addr, false if the range is out of bounds
+    fn write_memory(&mut self, addr: usize, bytes: &[u8]) -> bool {
+        if !self.in_range(addr, bytes.len()) {
+            return false;
+        }
+        let memory = &mut self.cpu.memory;
+        for (b, byte) in (addr..).zip(bytes) {
+            if b / 8 >= memory.len() {
+                memory.resize(b / 8 + 1, 0);
+            }
+            let mut cell = memory[b / 8].to_le_bytes();
+            cell[b % 8] = *byte;
+            memory[b / 8] = i64::from_le_bytes(cell);
+        }
+        true
+    }
+
+    // Executes one instruction, returns the reason to stop if it could not continue
+    fn step(&mut self) -> Result<(), Stop> {
+        let cpu = &mut self.cpu;
+        if !cpu.running() {
+            return Err(Stop::Exited);
+        }
+        match cpu.memory.get(cpu.ip) {
+            None => return Err(Stop::Signal(SIGSEGV)),
+            Some(inst) if !OPCODES.contains(&(inst % 100)) => return Err(Stop::Signal(SIGILL)),
+            _ => {}
+        }
+        // the cpu panics on missing operands, negative addresses and immediate destinations
+        let op = Op::decode(&cpu.memory, cpu.ip).ok_or(Stop::Signal(SIGSEGV))?;
+        if let Some(dst) = op.dst_index() {
+            if op.params[dst].0 == Mode::Immediate {
+                return Err(Stop::Signal(SIGILL));
+            }
+        }
+        for &(mode, val) in &op.params {
+            let addr = match mode {
+                Mode::Position => Some(val),
+                Mode::Relative => (cpu.rbp as i64).checked_add(val),
+                Mode::Immediate => continue,
+            };
+            if addr.map_or(true, |addr| addr < 0) {
+                return Err(Stop::Signal(SIGSEGV));
+            }
+        }
+        match cpu.step_event() {
+            Some(Event::InputRequired) => Err(Stop::Signal(SIGTRAP)),
+            _ if cpu.overflow().is_some() => Err(Stop::Signal(SIGFPE)),
+            _ if !cpu.running() => Err(Stop::Exited),
+            _ => Ok(()),
+        }
+    }
+
+    fn resume(&mut self, conn: &mut Connection) -> io::Result<Stop> {
+        let mut steps = 0;
+        loop {
+            if let Err(stop) = self.step() {
+                return Ok(stop);
+            }
+            if self.breakpoints.contains(&self.cpu.ip) {
+                return Ok(Stop::Signal(SIGTRAP));
+            }
+            steps += 1;
+            if steps % INTERRUPT_INTERVAL == 0 {
+                self.forward_output(conn)?;
+                if conn.interrupted()? {
+                    return Ok(Stop::Signal(SIGINT));
+                }
+            }
+        }
+    }
+
+    fn forward_output(&mut self, conn: &mut Connection) -> io::Result<()> {
+        while let Some(val) = self.cpu.output.pop_front() {
+            conn.send(&format!("O{}", to_hex(format!("{}\n", val).as_bytes())))?;
+        }
+        Ok(())
+    }
+}
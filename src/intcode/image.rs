@@ -0,0 +1,222 @@
+use super::IntCodeCpu;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::str::{self, FromStr};
+
+// Program images can be stored as
+//  - text: comma separated values, as given by the puzzles
+//  - lines: one value per line
+//  - binary: the magic bytes "INTC", a u32 version, a u64 length and `length` i64 values, all
+//    little-endian
+// Text images may contain `#` comments up to the end of the line and mix commas and newlines.
+
+const MAGIC: &[u8; 4] = b"INTC";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Lines,
+    Binary,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "lines" => Ok(Format::Lines),
+            "binary" => Ok(Format::Binary),
+            _ => Err(format!("unknown image format: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ImageError {
+    InvalidValue { line: usize, value: String },
+    InvalidText,
+    InvalidHeader,
+    UnsupportedVersion(u32),
+    Truncated { expected: usize, found: usize },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageError::InvalidValue { line, value } => {
+                write!(f, "invalid value '{}' in line {}", value, line)
+            }
+            ImageError::InvalidText => write!(f, "image is neither binary nor utf-8 text"),
+            ImageError::InvalidHeader => write!(f, "binary image header is incomplete"),
+            ImageError::UnsupportedVersion(version) => {
+                write!(f, "unsupported binary image version {}", version)
+            }
+            ImageError::Truncated { expected, found } => write!(
+                f,
+                "truncated binary image: expected {} values, found {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+pub fn detect(image: &[u8]) -> Format {
+    if image.starts_with(MAGIC) {
+        return Format::Binary;
+    }
+    let text = String::from_utf8_lossy(image);
+    let is_lines = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap())
+        .all(|line| !line.contains(','));
+    if is_lines && text.lines().count() > 1 {
+        Format::Lines
+    } else {
+        Format::Text
+    }
+}
+
+pub fn load(image: &[u8]) -> Result<Vec<i64>, ImageError> {
+    match detect(image) {
+        Format::Binary => decode_binary(image),
+        Format::Text | Format::Lines => {
+            let text = str::from_utf8(image).map_err(|_| ImageError::InvalidText)?;
+            parse_text(text)
+        }
+    }
+}
+
+// Parses comma or newline separated values, ignoring `#` comments
+pub fn parse_text(text: &str) -> Result<Vec<i64>, ImageError> {
+    let mut program = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        for value in line.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+            program.push(value.parse().map_err(|_| ImageError::InvalidValue {
+                line: i + 1,
+                value: value.to_string(),
+            })?);
+        }
+    }
+    Ok(program)
+}
+
+fn decode_binary(image: &[u8]) -> Result<Vec<i64>, ImageError> {
+    if image.len() < HEADER_LEN {
+        return Err(ImageError::InvalidHeader);
+    }
+    let version = u32::from_le_bytes(image[4..8].try_into().unwrap());
+    if version != VERSION {
+        return Err(ImageError::UnsupportedVersion(version));
+    }
+    let len = u64::from_le_bytes(image[8..16].try_into().unwrap()) as usize;
+    let values = &image[HEADER_LEN..];
+    if values.len() / 8 < len {
+        return Err(ImageError::Truncated {
+            expected: len,
+            found: values.len() / 8,
+        });
+    }
+    Ok(values
+        .chunks_exact(8)
+        .take(len)
+        .map(|v| i64::from_le_bytes(v.try_into().unwrap()))
+        .collect())
+}
+
+pub fn encode(program: &[i64], format: Format) -> Vec<u8> {
+    let join = |sep| {
+        let mut text = program
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(sep);
+        text.push('\n');
+        text.into_bytes()
+    };
+    match format {
+        Format::Text => join(","),
+        Format::Lines => join("\n"),
+        Format::Binary => {
+            let mut image = Vec::with_capacity(HEADER_LEN + 8 * program.len());
+            image.extend_from_slice(MAGIC);
+            image.extend_from_slice(&VERSION.to_le_bytes());
+            image.extend_from_slice(&(program.len() as u64).to_le_bytes());
+            for value in program {
+                image.extend_from_slice(&value.to_le_bytes());
+            }
+            image
+        }
+    }
+}
+
+impl IntCodeCpu {
+    pub fn from_image(image: &[u8]) -> Result<IntCodeCpu, ImageError> {
+        Ok(IntCodeCpu::from_memory(load(image)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: [i64; 7] = [1102, 34_915_192, 34_915_192, 7, 4, 7, 99];
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"1,2,3\n"), Format::Text);
+        assert_eq!(detect(b"1\n2\n3\n"), Format::Lines);
+        assert_eq!(detect(b"# a, b\n1\n2\n"), Format::Lines);
+        assert_eq!(detect(&encode(&PROGRAM, Format::Binary)), Format::Binary);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for format in &[Format::Text, Format::Lines, Format::Binary] {
+            assert_eq!(load(&encode(&PROGRAM, *format)), Ok(PROGRAM.to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        let text = "# multiply\n1102,34915192,  # a\n34915192,7\n\n4,7 # output\n99\n";
+        assert_eq!(load(text.as_bytes()), Ok(PROGRAM.to_vec()));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            load(b"1,2\n3,x\n"),
+            Err(ImageError::InvalidValue {
+                line: 2,
+                value: "x".to_string()
+            })
+        );
+
+        let mut image = encode(&PROGRAM, Format::Binary);
+        image.truncate(image.len() - 4);
+        assert_eq!(
+            load(&image),
+            Err(ImageError::Truncated {
+                expected: 7,
+                found: 6
+            })
+        );
+
+        image[4] = 2;
+        assert_eq!(load(&image), Err(ImageError::UnsupportedVersion(2)));
+    }
+
+    #[test]
+    fn test_from_image() {
+        let mut cpu = IntCodeCpu::from_image(&encode(&PROGRAM, Format::Binary)).unwrap();
+        cpu.run();
+        assert_eq!(cpu.output.pop_front(), Some(34_915_192 * 34_915_192));
+    }
+}
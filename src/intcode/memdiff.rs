@@ -0,0 +1,193 @@
+use super::IntCodeCpu;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Change {
+    pub addr: usize,
+    pub old: i64,
+    pub new: i64,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>6}: {} -> {}", self.addr, self.old, self.new)
+    }
+}
+
+// Cells past the end of a memory image read as 0, just like in the cpu
+fn cell(memory: &[i64], addr: usize) -> i64 {
+    memory.get(addr).copied().unwrap_or(0)
+}
+
+pub fn diff(before: &IntCodeCpu, after: &IntCodeCpu) -> Vec<Change> {
+    diff_memory(before.memory(), after.memory())
+}
+
+pub fn diff_memory(before: &[i64], after: &[i64]) -> Vec<Change> {
+    (0..before.len().max(after.len()))
+        .map(|addr| Change {
+            addr,
+            old: cell(before, addr),
+            new: cell(after, addr),
+        })
+        .filter(|change| change.old != change.new)
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    ChangedBy(i64),
+    EqualTo(i64),
+}
+
+impl Filter {
+    pub fn matches(self, old: i64, new: i64) -> bool {
+        match self {
+            Filter::Changed => old != new,
+            Filter::Unchanged => old == new,
+            Filter::Increased => new > old,
+            Filter::Decreased => new < old,
+            Filter::ChangedBy(delta) => new.checked_sub(old) == Some(delta),
+            Filter::EqualTo(val) => new == val,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseFilterError(String);
+
+impl fmt::Display for ParseFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid filter: {}", self.0)
+    }
+}
+
+// Filters are written as "changed", "unchanged", "inc", "dec", "+N"/"-N" for a change by N and
+// "=N" for cells holding N
+impl FromStr for Filter {
+    type Err = ParseFilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |n: &str| {
+            n.parse::<i64>()
+                .map_err(|_| ParseFilterError(s.to_string()))
+        };
+        match s {
+            "changed" => Ok(Filter::Changed),
+            "unchanged" => Ok(Filter::Unchanged),
+            "inc" => Ok(Filter::Increased),
+            "dec" => Ok(Filter::Decreased),
+            _ if s.starts_with('+') || s.starts_with('-') => Ok(Filter::ChangedBy(number(s)?)),
+            _ if s.starts_with('=') => Ok(Filter::EqualTo(number(&s[1..])?)),
+            _ => Err(ParseFilterError(s.to_string())),
+        }
+    }
+}
+
+// Narrows down the set of candidate addresses over a series of memory snapshots, keeping only
+// cells that changed consistently with every filter applied so far
+pub struct Search {
+    candidates: BTreeSet<usize>,
+    last: Vec<i64>,
+}
+
+impl Search {
+    pub fn new(cpu: &IntCodeCpu) -> Search {
+        Search {
+            candidates: (0..cpu.memory().len()).collect(),
+            last: cpu.memory().to_vec(),
+        }
+    }
+
+    // Takes a new snapshot without narrowing down the candidates
+    pub fn snapshot(&mut self, cpu: &IntCodeCpu) {
+        // cells allocated since the last snapshot start out as candidates
+        self.candidates.extend(self.last.len()..cpu.memory().len());
+        self.last = cpu.memory().to_vec();
+    }
+
+    pub fn update(&mut self, cpu: &IntCodeCpu, filter: Filter) -> Vec<Change> {
+        let memory = cpu.memory();
+        self.candidates.extend(self.last.len()..memory.len());
+        let last = &self.last;
+        self.candidates
+            .retain(|&addr| filter.matches(cell(last, addr), cell(memory, addr)));
+        let matches = self
+            .candidates
+            .iter()
+            .map(|&addr| Change {
+                addr,
+                old: cell(last, addr),
+                new: cell(memory, addr),
+            })
+            .collect();
+        self.last = memory.to_vec();
+        matches
+    }
+
+    pub fn candidates(&self) -> &BTreeSet<usize> {
+        &self.candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let before = IntCodeCpu::from_code("3,10,1001,11,5,11,4,10,99");
+        let mut after = before.clone();
+        after.input.push_back(7);
+        after.run();
+        assert_eq!(
+            diff(&before, &after),
+            vec![
+                Change {
+                    addr: 10,
+                    old: 0,
+                    new: 7
+                },
+                Change {
+                    addr: 11,
+                    old: 0,
+                    new: 5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        assert_eq!("inc".parse(), Ok(Filter::Increased));
+        assert_eq!("-1".parse(), Ok(Filter::ChangedBy(-1)));
+        assert_eq!("+3".parse(), Ok(Filter::ChangedBy(3)));
+        assert_eq!("=42".parse(), Ok(Filter::EqualTo(42)));
+        assert!("foo".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn test_search() {
+        // counts cell 20 down and cell 21 up on every input, cell 22 follows the input
+        let code = "3,22,1001,20,-1,20,1001,21,1,21,1105,1,0,0,0,0,0,0,0,0,10,0,0";
+        let mut cpu = IntCodeCpu::from_code(code);
+        cpu.run_until_event();
+        let mut search = Search::new(&cpu);
+
+        for (input, filter) in &[(5, Filter::Changed), (5, Filter::Decreased)] {
+            cpu.input.push_back(*input);
+            cpu.run_until_event();
+            search.update(&cpu, *filter);
+        }
+        assert_eq!(
+            search.candidates().iter().copied().collect::<Vec<_>>(),
+            vec![20]
+        );
+    }
+}
@@ -0,0 +1,777 @@
+use super::disasm::{Mode, Op, ADD, EQ, HLT, IN, JNZ, JZ, LT, MUL, OPCODES, OUT, RBO};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+// Peephole optimizer for Intcode programs. The analysis runs the program abstractly from
+// address 0: every cell holds a set of possible values or an unknown value, and rbp is tracked
+// exactly, with one state per instruction and rbp value. Branches are followed both ways unless
+// the condition is known, and jumps through memory go to every value the cell may hold.
+//
+// An instruction is only rewritten if its cells hold their values from the image whenever it
+// runs and no other instruction reads them as data, and an operand is only inlined if its cell
+// holds the value from the image whenever it is read. Writes that can only happen after an
+// instruction ran for the last time do not matter, so straight-line code that overwrites its
+// own instructions behind itself is still optimized. Programs are assumed to run without
+// peripherals.
+
+// Values a cell may hold before it is treated as unknown
+const MAX_VALUES: usize = 64;
+// rbp values an instruction may run with before the analysis gives up, which stops unbounded
+// recursion
+const MAX_FRAMES: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RewriteKind {
+    InlineConstant,
+    FoldConstant,
+    ResolveBranch,
+    ThreadJump,
+}
+
+impl fmt::Display for RewriteKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            RewriteKind::InlineConstant => "inline constant",
+            RewriteKind::FoldConstant => "fold constant",
+            RewriteKind::ResolveBranch => "resolve branch",
+            RewriteKind::ThreadJump => "thread jump",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rewrite {
+    pub addr: usize,
+    pub kind: RewriteKind,
+    pub before: Vec<i64>,
+    pub after: Vec<i64>,
+}
+
+impl fmt::Display for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |cells: &[i64]| {
+            cells
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        write!(
+            f,
+            "{:>6}: {:<16} {} -> {}",
+            self.addr,
+            self.kind.to_string(),
+            join(&self.before),
+            join(&self.after)
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OptimizeError {
+    // rbp is unknown at the instruction or takes too many values there
+    RelativeAddressing { addr: usize },
+    // The instruction's opcode cell may be written before it runs
+    SelfModifying { addr: usize },
+    // An address operand of the instruction is computed from unknown values
+    DynamicAddress { addr: usize },
+    // The jump target of the instruction is unknown
+    IndirectJump { addr: usize },
+    // Execution can leave the program image
+    OutOfBounds { addr: usize },
+}
+
+impl fmt::Display for OptimizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptimizeError::RelativeAddressing { addr } => {
+                write!(f, "rbp can not be tracked at {}", addr)
+            }
+            OptimizeError::SelfModifying { addr } => {
+                write!(f, "instruction at {} is modified by the program", addr)
+            }
+            OptimizeError::DynamicAddress { addr } => {
+                write!(f, "operand address of instruction at {} is unknown", addr)
+            }
+            OptimizeError::IndirectJump { addr } => {
+                write!(f, "jump target of instruction at {} is unknown", addr)
+            }
+            OptimizeError::OutOfBounds { addr } => {
+                write!(f, "instruction at {} leaves the program image", addr)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Optimized {
+    pub program: Vec<i64>,
+    pub rewrites: Vec<Rewrite>,
+}
+
+// The values a cell may hold
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Set(Rc<BTreeSet<i64>>),
+    Unknown,
+}
+
+impl Value {
+    fn of(val: i64) -> Value {
+        Value::Set(Rc::new(vec![val].into_iter().collect()))
+    }
+
+    fn from_set(set: BTreeSet<i64>) -> Value {
+        if set.len() > MAX_VALUES {
+            Value::Unknown
+        } else {
+            Value::Set(Rc::new(set))
+        }
+    }
+
+    fn join(&self, other: &Value) -> Value {
+        match (self, other) {
+            (Value::Set(a), Value::Set(b)) if b.is_subset(a) => self.clone(),
+            (Value::Set(a), Value::Set(b)) => Value::from_set(a.union(b).cloned().collect()),
+            _ => Value::Unknown,
+        }
+    }
+
+    // Applies f to all pairs of values, unknown if it fails for any of them
+    fn combine<F: Fn(i64, i64) -> Option<i64>>(&self, other: &Value, f: F) -> Value {
+        match (self, other) {
+            (Value::Set(a), Value::Set(b)) => {
+                let mut set = BTreeSet::new();
+                for x in a.iter() {
+                    for y in b.iter() {
+                        match f(*x, *y) {
+                            Some(val) => set.insert(val),
+                            None => return Value::Unknown,
+                        };
+                    }
+                }
+                Value::from_set(set)
+            }
+            _ => Value::Unknown,
+        }
+    }
+}
+
+// rbp and the cells written so far; all other cells hold their value from the image
+#[derive(Clone, Debug)]
+struct State {
+    rbp: i64,
+    cells: BTreeMap<usize, Value>,
+}
+
+impl State {
+    fn get(&self, program: &[i64], addr: usize) -> Value {
+        match self.cells.get(&addr) {
+            Some(val) => val.clone(),
+            None => Value::of(program.get(addr).cloned().unwrap_or(0)),
+        }
+    }
+
+    fn is_original(&self, program: &[i64], addr: usize) -> bool {
+        self.cells.get(&addr).map_or(true, |val| {
+            *val == Value::of(program.get(addr).cloned().unwrap_or(0))
+        })
+    }
+
+    // Adds the values of the other state with the same rbp, returns true if anything changed
+    fn join(&mut self, program: &[i64], other: &State) -> bool {
+        let mut changed = false;
+        for (addr, val) in &other.cells {
+            if self.cells.get(addr) != Some(val) {
+                let joined = self.get(program, *addr).join(val);
+                changed |= self.cells.insert(*addr, joined.clone()) != Some(joined);
+            }
+        }
+        for (addr, val) in self.cells.iter_mut() {
+            if !other.cells.contains_key(addr) {
+                let joined = val.join(&other.get(program, *addr));
+                changed |= *val != joined;
+                *val = joined;
+            }
+        }
+        changed
+    }
+}
+
+struct Analysis {
+    ops: BTreeMap<usize, Op>,
+    // Addresses where execution stops on an unknown opcode
+    crashes: BTreeSet<usize>,
+    // Instructions that may run with some of their cells changed
+    modified: HashSet<usize>,
+    // Cells read as data, with the instruction and parameter reading them
+    reads: HashSet<(usize, usize, usize)>,
+    // Position parameters that may read a cell after the program changed it
+    changed_reads: HashSet<(usize, usize)>,
+}
+
+impl Analysis {
+    // The operand can be replaced by the value its cell has in the image
+    fn is_constant(&self, program: &[i64], op: &Op, i: usize) -> bool {
+        match op.params[i] {
+            (Mode::Position, val) => {
+                Some(i) != op.dst_index()
+                    && val >= 0
+                    && (val as usize) < program.len()
+                    && !self.changed_reads.contains(&(op.addr, i))
+            }
+            _ => false,
+        }
+    }
+
+    // Instructions that run unchanged, do not overlap other code and have a destination the
+    // cpu accepts
+    fn is_candidate(&self, op: &Op) -> bool {
+        let inside = op.addr + 1..op.cells().end;
+        !self.modified.contains(&op.addr)
+            && self.ops.range(inside.clone()).next().is_none()
+            && self.crashes.range(inside).next().is_none()
+            && self
+                .ops
+                .range(..op.addr)
+                .next_back()
+                .map_or(true, |(_, prev)| prev.cells().end <= op.addr)
+            && op
+                .dst_index()
+                .map_or(true, |i| op.params[i].0 != Mode::Immediate)
+    }
+
+    // The instructions that may be rewritten: candidates whose cells are not read as data by
+    // any instruction, not counting reads that are inlined because the reader is rewritten too
+    fn stable(&self, program: &[i64]) -> BTreeSet<usize> {
+        let mut stable: BTreeSet<usize> = self
+            .ops
+            .values()
+            .filter(|op| self.is_candidate(op))
+            .map(|op| op.addr)
+            .collect();
+        loop {
+            let read: HashSet<usize> = self
+                .reads
+                .iter()
+                .filter(|(addr, i, _)| {
+                    !stable.contains(addr) || !self.is_constant(program, &self.ops[addr], *i)
+                })
+                .map(|(_, _, cell)| *cell)
+                .collect();
+            let next: BTreeSet<usize> = stable
+                .iter()
+                .cloned()
+                .filter(|addr| self.ops[addr].cells().all(|c| !read.contains(&c)))
+                .collect();
+            if next.len() == stable.len() {
+                return stable;
+            }
+            stable = next;
+        }
+    }
+
+    // The cells a position or relative operand may refer to. The operand cell itself may have
+    // been written, e.g. to index an array.
+    fn addresses(
+        &self,
+        program: &[i64],
+        op: &Op,
+        i: usize,
+        state: &State,
+    ) -> Result<Vec<usize>, OptimizeError> {
+        let vals = match state.get(program, op.addr + 1 + i) {
+            Value::Set(vals) => vals,
+            Value::Unknown => return Err(OptimizeError::DynamicAddress { addr: op.addr }),
+        };
+        vals.iter()
+            .map(|val| {
+                let addr = match op.params[i].0 {
+                    Mode::Relative => state.rbp.checked_add(*val),
+                    _ => Some(*val),
+                };
+                match addr {
+                    Some(addr) if addr >= 0 => Ok(addr as usize),
+                    _ => Err(OptimizeError::OutOfBounds { addr: op.addr }),
+                }
+            })
+            .collect()
+    }
+
+    fn operand(
+        &mut self,
+        program: &[i64],
+        op: &Op,
+        i: usize,
+        state: &State,
+    ) -> Result<Value, OptimizeError> {
+        if op.params[i].0 == Mode::Immediate {
+            return Ok(state.get(program, op.addr + 1 + i));
+        }
+        let mut value: Option<Value> = None;
+        for cell in self.addresses(program, op, i, state)? {
+            self.reads.insert((op.addr, i, cell));
+            if !state.is_original(program, cell) {
+                self.changed_reads.insert((op.addr, i));
+            }
+            let val = state.get(program, cell);
+            value = Some(value.map_or(val.clone(), |v| v.join(&val)));
+        }
+        Ok(value.unwrap_or(Value::Unknown))
+    }
+
+    // Stores the value in the destination, or adds it to every cell it may go to
+    fn store(
+        &self,
+        program: &[i64],
+        op: &Op,
+        i: usize,
+        state: &mut State,
+        val: Value,
+    ) -> Result<(), OptimizeError> {
+        let cells = self.addresses(program, op, i, state)?;
+        let exact = cells.len() == 1;
+        for cell in cells {
+            let val = match exact {
+                true => val.clone(),
+                false => state.get(program, cell).join(&val),
+            };
+            state.cells.insert(cell, val);
+        }
+        Ok(())
+    }
+
+    // Runs the instruction at addr in the state, returns the states it can continue with
+    fn step(
+        &mut self,
+        program: &[i64],
+        addr: usize,
+        state: &State,
+    ) -> Result<Vec<(usize, State)>, OptimizeError> {
+        if addr >= program.len() {
+            return Err(OptimizeError::OutOfBounds { addr });
+        }
+        if !state.is_original(program, addr) {
+            return Err(OptimizeError::SelfModifying { addr });
+        }
+        if !OPCODES.contains(&(program[addr] % 100)) {
+            self.crashes.insert(addr);
+            return Ok(vec![]);
+        }
+        let op = Op::decode(program, addr).ok_or(OptimizeError::OutOfBounds { addr })?;
+        if op.cells().any(|c| state.cells.contains_key(&c)) {
+            self.modified.insert(addr);
+        }
+        self.ops.insert(addr, op.clone());
+
+        let mut next = vec![];
+        match op.opcode {
+            // immediate destinations crash the cpu
+            _ if op.dst_index().map(|i| op.params[i].0) == Some(Mode::Immediate) => {}
+            ADD | MUL | LT | EQ => {
+                let a = self.operand(program, &op, 0, state)?;
+                let b = self.operand(program, &op, 1, state)?;
+                let result = match op.opcode {
+                    ADD => a.combine(&b, i64::checked_add),
+                    MUL => a.combine(&b, i64::checked_mul),
+                    LT => a.combine(&b, |x, y| Some((x < y) as i64)),
+                    _ => a.combine(&b, |x, y| Some((x == y) as i64)),
+                };
+                let mut state = state.clone();
+                self.store(program, &op, 2, &mut state, result)?;
+                next.push((addr + 4, state));
+            }
+            IN => {
+                let mut state = state.clone();
+                self.store(program, &op, 0, &mut state, Value::Unknown)?;
+                next.push((addr + 2, state));
+            }
+            OUT => {
+                self.operand(program, &op, 0, state)?;
+                next.push((addr + 2, state.clone()));
+            }
+            JNZ | JZ => {
+                let jump_if = |cond: &i64| (*cond != 0) == (op.opcode == JNZ);
+                let (taken, not_taken) = match self.operand(program, &op, 0, state)? {
+                    Value::Set(conds) => (conds.iter().any(jump_if), !conds.iter().all(jump_if)),
+                    Value::Unknown => (true, true),
+                };
+                if taken {
+                    let targets = match self.operand(program, &op, 1, state)? {
+                        Value::Set(targets) => targets,
+                        Value::Unknown => return Err(OptimizeError::IndirectJump { addr }),
+                    };
+                    for &target in targets.iter() {
+                        if target < 0 {
+                            return Err(OptimizeError::OutOfBounds { addr });
+                        }
+                        next.push((target as usize, state.clone()));
+                    }
+                }
+                if not_taken {
+                    next.push((addr + 3, state.clone()));
+                }
+            }
+            RBO => {
+                let offsets = match self.operand(program, &op, 0, state)? {
+                    Value::Set(offsets) => offsets,
+                    Value::Unknown => return Err(OptimizeError::RelativeAddressing { addr }),
+                };
+                for &offset in offsets.iter() {
+                    let mut state = state.clone();
+                    state.rbp = state
+                        .rbp
+                        .checked_add(offset)
+                        .ok_or(OptimizeError::RelativeAddressing { addr })?;
+                    next.push((addr + 2, state));
+                }
+            }
+            HLT => {}
+            _ => unreachable!(),
+        }
+        Ok(next)
+    }
+}
+
+fn analyze(program: &[i64]) -> Result<Analysis, OptimizeError> {
+    let mut analysis = Analysis {
+        ops: BTreeMap::new(),
+        crashes: BTreeSet::new(),
+        modified: HashSet::new(),
+        reads: HashSet::new(),
+        changed_reads: HashSet::new(),
+    };
+    let start = State {
+        rbp: 0,
+        cells: BTreeMap::new(),
+    };
+    let mut states = HashMap::new();
+    let mut frames: HashMap<usize, usize> = HashMap::new();
+    states.insert((0, 0), start);
+    frames.insert(0, 1);
+    // visited in address order, which keeps loops from being revisited for every state change
+    let mut to_visit = BTreeSet::new();
+    to_visit.insert((0, 0));
+
+    while let Some((addr, rbp)) = to_visit.pop_first() {
+        let state = states[&(addr, rbp)].clone();
+        for (next_addr, next) in analysis.step(program, addr, &state)? {
+            let key = (next_addr, next.rbp);
+            match states.get_mut(&key) {
+                Some(known) => {
+                    if known.join(program, &next) {
+                        to_visit.insert(key);
+                    }
+                }
+                None => {
+                    let count = frames.entry(next_addr).or_insert(0);
+                    *count += 1;
+                    if *count > MAX_FRAMES {
+                        return Err(OptimizeError::RelativeAddressing { addr: next_addr });
+                    }
+                    states.insert(key, next);
+                    to_visit.insert(key);
+                }
+            }
+        }
+    }
+
+    Ok(analysis)
+}
+
+fn fold(op: &Op) -> Option<Op> {
+    if ![ADD, MUL, LT, EQ].contains(&op.opcode) {
+        return None;
+    }
+    let (a, b) = match (op.params[0], op.params[1]) {
+        ((Mode::Immediate, a), (Mode::Immediate, b)) => (a, b),
+        _ => return None,
+    };
+    let result = match op.opcode {
+        ADD => a.checked_add(b)?,
+        MUL => a.checked_mul(b)?,
+        LT => (a < b) as i64,
+        _ => (a == b) as i64,
+    };
+    let folded = Op {
+        addr: op.addr,
+        opcode: ADD,
+        params: vec![
+            (Mode::Immediate, result),
+            (Mode::Immediate, 0),
+            op.params[2],
+        ],
+    };
+    if folded == *op {
+        None
+    } else {
+        Some(folded)
+    }
+}
+
+fn record(rewrites: &mut Vec<Rewrite>, kind: RewriteKind, before: &Op, after: &Op) {
+    rewrites.push(Rewrite {
+        addr: before.addr,
+        kind,
+        before: before.encode(),
+        after: after.encode(),
+    });
+}
+
+pub fn optimize(program: &[i64]) -> Result<Optimized, OptimizeError> {
+    let analysis = analyze(program)?;
+    let stable = analysis.stable(program);
+    let mut rewrites = vec![];
+    let mut resolved = BTreeMap::new();
+
+    for op in analysis.ops.values().filter(|op| stable.contains(&op.addr)) {
+        let mut new_op = op.clone();
+
+        for i in 0..new_op.params.len() {
+            if analysis.is_constant(program, op, i) {
+                let before = new_op.clone();
+                new_op.params[i] = (Mode::Immediate, program[op.params[i].1 as usize]);
+                record(&mut rewrites, RewriteKind::InlineConstant, &before, &new_op);
+            }
+        }
+
+        if let Some(folded) = fold(&new_op) {
+            record(&mut rewrites, RewriteKind::FoldConstant, &new_op, &folded);
+            new_op = folded;
+        }
+
+        if new_op.is_jump()
+            && new_op.branch() == Some(true)
+            && new_op.params[0] != (Mode::Immediate, 1)
+        {
+            let before = new_op.clone();
+            new_op.opcode = JNZ;
+            new_op.params[0] = (Mode::Immediate, 1);
+            record(&mut rewrites, RewriteKind::ResolveBranch, &before, &new_op);
+        }
+
+        resolved.insert(op.addr, new_op);
+    }
+
+    // Follow chains of unconditional jumps, and skip jumps that are never taken
+    let thread = |mut target: usize| {
+        let mut seen = HashSet::new();
+        while seen.insert(target) {
+            match resolved.get(&target) {
+                Some(op) if op.is_jump() => match (op.branch(), op.params[1]) {
+                    (Some(true), (Mode::Immediate, next)) if next >= 0 => target = next as usize,
+                    (Some(false), _) => target += 3,
+                    _ => break,
+                },
+                _ => break,
+            }
+        }
+        target
+    };
+
+    let mut threaded = vec![];
+    for op in resolved.values().filter(|op| op.is_jump()) {
+        if let (Mode::Immediate, target) = op.params[1] {
+            if op.branch() == Some(false) || target < 0 {
+                continue;
+            }
+            let new_target = thread(target as usize);
+            if new_target != target as usize {
+                let mut new_op = op.clone();
+                new_op.params[1] = (Mode::Immediate, new_target as i64);
+                threaded.push(new_op);
+            }
+        }
+    }
+    for new_op in threaded {
+        record(
+            &mut rewrites,
+            RewriteKind::ThreadJump,
+            &resolved[&new_op.addr],
+            &new_op,
+        );
+        resolved.insert(new_op.addr, new_op);
+    }
+
+    let mut optimized = program.to_vec();
+    for (addr, op) in &resolved {
+        if analysis.ops[addr] != *op {
+            for (i, cell) in op.encode().into_iter().enumerate() {
+                optimized[addr + i] = cell;
+            }
+        }
+    }
+
+    Ok(Optimized {
+        program: optimized,
+        rewrites,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{Event, IntCodeCpu};
+    use std::fs;
+
+    fn parse(code: &str) -> Vec<i64> {
+        code.trim()
+            .split(',')
+            .map(|x| x.trim().parse::<i64>().unwrap())
+            .collect()
+    }
+
+    fn run(program: &[i64], inputs: &[i64]) -> (Vec<i64>, IntCodeCpu) {
+        let mut cpu = IntCodeCpu::from_memory(program.to_vec());
+        cpu.input.extend(inputs);
+        let mut outputs = vec![];
+        while let Event::OutputAvailable(val) = cpu.run_until_event() {
+            outputs.push(val);
+        }
+        (outputs, cpu)
+    }
+
+    #[test]
+    fn test_rewrites() {
+        let program = parse("1001,20,0,21,8,20,22,23,1005,23,13,4,21,1106,0,17,99,4,23,99,7,0,7,0");
+        let optimized = optimize(&program).unwrap();
+        let kinds: Vec<_> = optimized
+            .rewrites
+            .iter()
+            .map(|r| (r.addr, r.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (0, RewriteKind::InlineConstant),
+                (4, RewriteKind::InlineConstant),
+                (4, RewriteKind::InlineConstant),
+                (4, RewriteKind::FoldConstant),
+                (13, RewriteKind::ResolveBranch),
+                (8, RewriteKind::ThreadJump),
+            ]
+        );
+        assert_eq!(
+            optimized.program,
+            parse("1101,7,0,21,1101,1,0,23,1005,23,17,4,21,1105,1,17,99,4,23,99,7,0,7,0")
+        );
+        assert_eq!(run(&program, &[]).0, vec![1]);
+        assert_eq!(run(&optimized.program, &[]).0, vec![1]);
+    }
+
+    #[test]
+    fn test_calls() {
+        // calls a function twice, which outputs 6 * 7 and returns through the stack
+        let program = parse(
+            "109,30,21101,9,0,0,1105,1,20,21101,16,0,0,1105,1,20,99,0,0,0,
+             1102,6,7,29,4,29,2106,0,0,0",
+        );
+        let optimized = optimize(&program).unwrap();
+        let kinds: Vec<_> = optimized
+            .rewrites
+            .iter()
+            .map(|r| (r.addr, r.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (20, RewriteKind::FoldConstant),
+                (26, RewriteKind::ResolveBranch),
+            ]
+        );
+        assert_eq!(
+            optimized.program,
+            parse(
+                "109,30,21101,9,0,0,1105,1,20,21101,16,0,0,1105,1,20,99,0,0,0,
+                 1101,42,0,29,4,29,2105,1,0,0"
+            )
+        );
+        assert_eq!(run(&optimized.program, &[]).0, vec![42, 42]);
+
+        // overwrites its first instruction once it no longer runs
+        let optimized = optimize(&parse("1,0,0,0,99")).unwrap();
+        assert_eq!(optimized.program, parse("1101,2,0,0,99"));
+        assert_eq!(run(&optimized.program, &[]).1.peek_memory(0), 2);
+    }
+
+    #[test]
+    fn test_refused() {
+        assert_eq!(
+            optimize(&parse("1101,98,1,4,0")).unwrap_err(),
+            OptimizeError::SelfModifying { addr: 4 }
+        );
+        assert_eq!(
+            optimize(&parse("3,3,4,0,99")).unwrap_err(),
+            OptimizeError::DynamicAddress { addr: 2 }
+        );
+        assert_eq!(
+            optimize(&parse("3,4,1105,1,0,99")).unwrap_err(),
+            OptimizeError::IndirectJump { addr: 2 }
+        );
+        assert_eq!(
+            optimize(&parse("3,3,109,0,99")).unwrap_err(),
+            OptimizeError::RelativeAddressing { addr: 2 }
+        );
+        // recurses forever
+        assert_eq!(
+            optimize(&parse("109,1,1105,1,0")).unwrap_err(),
+            OptimizeError::RelativeAddressing { addr: 0 }
+        );
+        assert_eq!(
+            optimize(&parse("1105,1,-1")).unwrap_err(),
+            OptimizeError::OutOfBounds { addr: 0 }
+        );
+    }
+
+    fn load(day: &str) -> Vec<i64> {
+        parse(&fs::read_to_string(format!("./input/day{}.in", day)).unwrap())
+    }
+
+    #[test]
+    fn test_puzzle_inputs() {
+        // the robot of day 11 sees a fixed pattern of panel colours
+        let colours: Vec<i64> = (0..300).map(|i| i % 3 % 2).collect();
+        let cases: Vec<(&str, Vec<Vec<i64>>)> = vec![
+            ("09", vec![vec![1], vec![2]]),
+            ("11", vec![vec![1], colours]),
+        ];
+        for (day, runs) in cases {
+            let program = load(day);
+            let optimized = optimize(&program).unwrap();
+            assert!(!optimized.rewrites.is_empty(), "day {}", day);
+            for inputs in runs {
+                let outputs = run(&program, &inputs).0;
+                assert!(!outputs.is_empty());
+                assert_eq!(outputs, run(&optimized.program, &inputs).0, "day {}", day);
+            }
+        }
+
+        for (noun, verb) in [(12, 2), (0, 0), (99, 99)] {
+            let mut program = load("02");
+            program[1] = noun;
+            program[2] = verb;
+            let optimized = optimize(&program).unwrap();
+            assert!(!optimized.rewrites.is_empty());
+            assert_eq!(
+                run(&program, &[]).1.peek_memory(0),
+                run(&optimized.program, &[]).1.peek_memory(0)
+            );
+        }
+
+        // opcodes patched from input, jump tables, arrays indexed by input and recursion of
+        // unbounded depth are out of reach
+        let refused = [
+            ("05", OptimizeError::SelfModifying { addr: 6 }),
+            ("07", OptimizeError::DynamicAddress { addr: 6 }),
+            ("13", OptimizeError::DynamicAddress { addr: 592 }),
+            ("15", OptimizeError::DynamicAddress { addr: 210 }),
+            ("17", OptimizeError::DynamicAddress { addr: 21 }),
+            ("19", OptimizeError::RelativeAddressing { addr: 303 }),
+        ];
+        for (day, error) in refused {
+            assert_eq!(optimize(&load(day)).unwrap_err(), error, "day {}", day);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+// A virtual device mapped into the memory of an IntCodeCpu. Reads and writes of operands within
+// the device's address range are forwarded to the device instead of touching memory.
+pub trait Peripheral: Send {
+    fn read(&mut self, addr: usize) -> i64;
+    fn write(&mut self, addr: usize, val: i64);
+}
+
+pub type SharedPeripheral = Arc<Mutex<dyn Peripheral>>;
+
+// Devices are shared between clones of a cpu
+#[derive(Clone, Default)]
+pub(crate) struct Peripherals {
+    devices: Vec<(Range<usize>, SharedPeripheral)>,
+}
+
+impl Peripherals {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    pub(crate) fn attach(&mut self, range: Range<usize>, device: SharedPeripheral) {
+        if let Some((other, _)) = self
+            .devices
+            .iter()
+            .find(|(other, _)| other.start < range.end && range.start < other.end)
+        {
+            panic!(
+                "peripheral at {:?} overlaps peripheral at {:?}",
+                range, other
+            );
+        }
+        self.devices.push((range, device));
+    }
+
+    pub(crate) fn detach_all(&mut self) {
+        self.devices.clear();
+    }
+
+    pub(crate) fn find(&self, addr: usize) -> Option<&SharedPeripheral> {
+        self.devices
+            .iter()
+            .find(|(range, _)| range.contains(&addr))
+            .map(|(_, device)| device)
+    }
+}
+
+impl fmt::Debug for Peripherals {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.devices.iter().map(|(range, _)| range))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::IntCodeCpu;
+
+    struct Recorder {
+        value: i64,
+        writes: Vec<(usize, i64)>,
+    }
+
+    impl Peripheral for Recorder {
+        fn read(&mut self, _addr: usize) -> i64 {
+            self.value
+        }
+
+        fn write(&mut self, addr: usize, val: i64) {
+            self.writes.push((addr, val));
+        }
+    }
+
+    #[test]
+    fn test_read_write() {
+        let recorder = Arc::new(Mutex::new(Recorder {
+            value: 21,
+            writes: vec![],
+        }));
+        // out = [100] * 2; [101] = out
+        let mut cpu = IntCodeCpu::from_code("1002,100,2,101,4,101,99");
+        cpu.attach_peripheral(100..102, recorder.clone());
+        cpu.run();
+
+        let recorder = recorder.lock().unwrap();
+        assert_eq!(recorder.writes, vec![(101, 42)]);
+        assert_eq!(cpu.output.pop_front(), Some(21));
+        assert_eq!(cpu.memory().len(), 7);
+    }
+
+    #[test]
+    fn test_unmapped_addresses() {
+        let recorder = Arc::new(Mutex::new(Recorder {
+            value: 0,
+            writes: vec![],
+        }));
+        let mut cpu = IntCodeCpu::from_code("3,0,4,0,99");
+        cpu.attach_peripheral(10..20, recorder.clone());
+        cpu.input.push_back(1234);
+        cpu.run();
+        assert_eq!(cpu.output.pop_front(), Some(1234));
+        assert!(recorder.lock().unwrap().writes.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_overlapping_ranges() {
+        let mut cpu = IntCodeCpu::from_code("99");
+        let recorder = || {
+            Arc::new(Mutex::new(Recorder {
+                value: 0,
+                writes: vec![],
+            }))
+        };
+        cpu.attach_peripheral(10..20, recorder());
+        cpu.attach_peripheral(15..25, recorder());
+    }
+}
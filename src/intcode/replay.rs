@@ -0,0 +1,237 @@
+use super::{Event, IntCodeCpu};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Replay files start with a header line followed by one "<step> <value>" line per input, where
+// step is the number of instructions the cpu had executed when it asked for the value.
+const HEADER: &str = "# intcode replay v1";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Entry {
+    pub step: u64,
+    pub value: i64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ReplayError {
+    // The program asked for input at another step than recorded
+    Mismatch { expected: u64, actual: u64 },
+    // The program asked for more input than recorded
+    Exhausted { step: u64 },
+    // The program halted before all recorded input was used
+    Unused { remaining: usize },
+    InvalidLine { line: usize },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Mismatch { expected, actual } => write!(
+                f,
+                "program asked for input at step {}, recorded at step {}",
+                actual, expected
+            ),
+            ReplayError::Exhausted { step } => {
+                write!(
+                    f,
+                    "program asked for input at step {} after the end of the replay",
+                    step
+                )
+            }
+            ReplayError::Unused { remaining } => {
+                write!(f, "program halted with {} recorded inputs left", remaining)
+            }
+            ReplayError::InvalidLine { line } => write!(f, "invalid replay entry in line {}", line),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl Replay {
+    pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+        let mut entries = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let step = parts.next().and_then(|s| s.parse().ok());
+            let value = parts.next().and_then(|v| v.parse().ok());
+            match (step, value, parts.next()) {
+                (Some(step), Some(value), None) => entries.push(Entry { step, value }),
+                _ => return Err(ReplayError::InvalidLine { line: i + 1 }),
+            }
+        }
+        Ok(Replay { entries })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Replay> {
+        Replay::parse(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for entry in &self.entries {
+            writeln!(f, "{} {}", entry.step, entry.value)?;
+        }
+        Ok(())
+    }
+}
+
+// Supplies input to a cpu and records it
+#[derive(Default)]
+pub struct Recorder {
+    replay: Replay,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+
+    pub fn supply(&mut self, cpu: &mut IntCodeCpu, value: i64) {
+        self.replay.entries.push(Entry {
+            step: cpu.steps(),
+            value,
+        });
+        cpu.input.push_back(value);
+    }
+
+    pub fn finish(self) -> Replay {
+        self.replay
+    }
+}
+
+// Supplies recorded input to a cpu, checking that it is requested at the recorded steps
+pub struct Player {
+    replay: Replay,
+    next: usize,
+}
+
+impl Player {
+    pub fn new(replay: Replay) -> Player {
+        Player { replay, next: 0 }
+    }
+
+    pub fn supply(&mut self, cpu: &mut IntCodeCpu) -> Result<(), ReplayError> {
+        let entry = self
+            .replay
+            .entries
+            .get(self.next)
+            .ok_or(ReplayError::Exhausted { step: cpu.steps() })?;
+        if entry.step != cpu.steps() {
+            return Err(ReplayError::Mismatch {
+                expected: entry.step,
+                actual: cpu.steps(),
+            });
+        }
+        cpu.input.push_back(entry.value);
+        self.next += 1;
+        Ok(())
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.replay.entries.len() - self.next
+    }
+}
+
+// Runs the cpu until it halts, feeding it the recorded input. Outputs are left in cpu.output.
+pub fn run_replay(cpu: &mut IntCodeCpu, replay: Replay) -> Result<(), ReplayError> {
+    let mut player = Player::new(replay);
+    loop {
+        match cpu.run_until_event() {
+            Event::InputRequired => player.supply(cpu)?,
+            Event::OutputAvailable(_) => {}
+            Event::Halted => break,
+        }
+    }
+    match player.remaining() {
+        0 => Ok(()),
+        remaining => Err(ReplayError::Unused { remaining }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // reads two values, outputs their sum and repeats while the sum is not zero
+    const CODE: &str = "3,15,3,16,1,15,16,17,4,17,1005,17,0,99,0,0,0,0";
+
+    fn record(inputs: &[i64]) -> (Vec<i64>, Replay) {
+        let mut cpu = IntCodeCpu::from_code(CODE);
+        let mut recorder = Recorder::new();
+        let mut inputs = inputs.iter();
+        loop {
+            match cpu.run_until_event() {
+                Event::InputRequired => recorder.supply(&mut cpu, *inputs.next().unwrap()),
+                Event::OutputAvailable(_) => {}
+                Event::Halted => break,
+            }
+        }
+        (cpu.output.into_iter().collect(), recorder.finish())
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let (outputs, replay) = record(&[3, 4, -2, 2]);
+        assert_eq!(outputs, vec![7, 0]);
+        assert_eq!(
+            replay.entries.iter().map(|e| e.step).collect::<Vec<_>>(),
+            vec![0, 1, 5, 6]
+        );
+
+        let replay = Replay::parse(&replay.to_string()).unwrap();
+        let mut cpu = IntCodeCpu::from_code(CODE);
+        assert_eq!(run_replay(&mut cpu, replay), Ok(()));
+        assert_eq!(cpu.output.into_iter().collect::<Vec<_>>(), outputs);
+    }
+
+    #[test]
+    fn test_replay_errors() {
+        let (_, mut replay) = record(&[3, 4, -2, 2]);
+        replay.entries[2].step = 4;
+        assert_eq!(
+            run_replay(&mut IntCodeCpu::from_code(CODE), replay.clone()),
+            Err(ReplayError::Mismatch {
+                expected: 4,
+                actual: 5
+            })
+        );
+
+        replay.entries.truncate(2);
+        assert_eq!(
+            run_replay(&mut IntCodeCpu::from_code(CODE), replay),
+            Err(ReplayError::Exhausted { step: 5 })
+        );
+
+        let (_, mut replay) = record(&[1, -1]);
+        replay.entries.push(Entry { step: 6, value: 0 });
+        assert_eq!(
+            run_replay(&mut IntCodeCpu::from_code(CODE), replay),
+            Err(ReplayError::Unused { remaining: 1 })
+        );
+
+        assert_eq!(
+            Replay::parse("# intcode replay v1\n0 1\n1\n"),
+            Err(ReplayError::InvalidLine { line: 3 })
+        );
+    }
+}
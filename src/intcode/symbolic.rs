@@ -0,0 +1,636 @@
+use super::disasm::{Mode, ADD, EQ, HLT, IN, JNZ, JZ, LT, MUL, OUT, RBO};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::ops;
+use std::rc::Rc;
+
+// Symbolic execution of Intcode programs. Memory cells and inputs may hold expressions over
+// named symbols instead of values. Arithmetic and comparisons build expression trees, branches
+// on symbolic conditions fork the execution and record the condition for each path.
+// Reading through a symbolic address gives an opaque load of that address. Instructions, store
+// addresses and jump targets have to stay concrete.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Const(i64),
+    Sym(Rc<str>),
+    Add(Rc<Expr>, Rc<Expr>),
+    Mul(Rc<Expr>, Rc<Expr>),
+    Lt(Rc<Expr>, Rc<Expr>),
+    Eq(Rc<Expr>, Rc<Expr>),
+    // The memory cell at the address, at the time it was read
+    Load(Rc<Expr>),
+}
+
+impl Expr {
+    pub fn sym(name: &str) -> Expr {
+        Expr::Sym(name.into())
+    }
+
+    pub fn value(&self) -> Option<i64> {
+        match self {
+            Expr::Const(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    fn terms(&self) -> (i64, Vec<(Expr, i64)>) {
+        match self {
+            Expr::Const(c) => (*c, vec![]),
+            Expr::Add(a, b) => {
+                let (c, mut terms) = a.terms();
+                let (d, others) = b.terms();
+                terms.extend(others);
+                (c.wrapping_add(d), terms)
+            }
+            Expr::Mul(a, k) if k.value().is_some() => {
+                (0, vec![((**a).clone(), k.value().unwrap())])
+            }
+            atom => (0, vec![(atom.clone(), 1)]),
+        }
+    }
+
+    fn from_terms(c: i64, terms: Vec<(Expr, i64)>) -> Expr {
+        let terms = terms
+            .into_iter()
+            .filter(|(_, k)| *k != 0)
+            .map(|(atom, k)| match k {
+                1 => atom,
+                k => Expr::Mul(Rc::new(atom), Rc::new(Expr::Const(k))),
+            });
+        let sum = terms.fold(None, |sum, term| match sum {
+            None => Some(term),
+            Some(sum) => Some(Expr::Add(Rc::new(sum), Rc::new(term))),
+        });
+        match (sum, c) {
+            (None, c) => Expr::Const(c),
+            (Some(sum), 0) => sum,
+            (Some(sum), c) => Expr::Add(Rc::new(sum), Rc::new(Expr::Const(c))),
+        }
+    }
+
+    pub fn lt(a: Expr, b: Expr) -> Expr {
+        match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const((a < b) as i64),
+            (a, b) if a == b => Expr::Const(0),
+            (a, b) => Expr::Lt(Rc::new(a), Rc::new(b)),
+        }
+    }
+
+    pub fn eq(a: Expr, b: Expr) -> Expr {
+        match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const((a == b) as i64),
+            (a, b) if a == b => Expr::Const(1),
+            (a, b) => Expr::Eq(Rc::new(a), Rc::new(b)),
+        }
+    }
+
+    pub fn eval(&self, values: &BTreeMap<String, i64>) -> Option<i64> {
+        Some(match self {
+            Expr::Const(val) => *val,
+            Expr::Sym(name) => *values.get(&**name)?,
+            Expr::Add(a, b) => a.eval(values)?.wrapping_add(b.eval(values)?),
+            Expr::Mul(a, b) => a.eval(values)?.wrapping_mul(b.eval(values)?),
+            Expr::Lt(a, b) => (a.eval(values)? < b.eval(values)?) as i64,
+            Expr::Eq(a, b) => (a.eval(values)? == b.eval(values)?) as i64,
+            Expr::Load(_) => return None,
+        })
+    }
+
+    // Returns the expression as constant plus coefficients per symbol, if it is linear
+    pub fn linear(&self) -> Option<(i64, BTreeMap<String, i64>)> {
+        match self {
+            Expr::Const(val) => Some((*val, BTreeMap::new())),
+            Expr::Sym(name) => Some((0, Some((name.to_string(), 1)).into_iter().collect())),
+            Expr::Add(a, b) => {
+                let (c, mut coeffs) = a.linear()?;
+                let (d, others) = b.linear()?;
+                for (name, coeff) in others {
+                    let sum = coeffs.entry(name).or_insert(0);
+                    *sum = sum.wrapping_add(coeff);
+                }
+                Some((c.wrapping_add(d), coeffs))
+            }
+            Expr::Mul(a, b) => {
+                let ((c, coeffs), factor) = match (a.linear()?, b.linear()?) {
+                    ((c, coeffs), (d, ref others)) if others.is_empty() => ((c, coeffs), d),
+                    ((d, ref others), (c, coeffs)) if others.is_empty() => ((c, coeffs), d),
+                    _ => return None,
+                };
+                let coeffs = coeffs
+                    .into_iter()
+                    .map(|(n, k)| (n, k.wrapping_mul(factor)))
+                    .collect();
+                Some((c.wrapping_mul(factor), coeffs))
+            }
+            Expr::Lt(..) | Expr::Eq(..) | Expr::Load(_) => None,
+        }
+    }
+
+    fn is_compound(&self) -> bool {
+        !matches!(self, Expr::Const(_) | Expr::Sym(_) | Expr::Load(_))
+    }
+}
+
+// Sums and constant multiples are kept in a canonical linear form over atoms, which are all
+// other expressions: terms in first-appearance order followed by the constant. That way terms
+// that cancel out disappear.
+impl ops::Add for Expr {
+    type Output = Expr;
+
+    fn add(self, other: Expr) -> Expr {
+        let (c, mut terms) = self.terms();
+        let (d, others) = other.terms();
+        for (atom, k) in others {
+            match terms.iter_mut().find(|(other, _)| *other == atom) {
+                Some((_, coeff)) => *coeff = coeff.wrapping_add(k),
+                None => terms.push((atom, k)),
+            }
+        }
+        Expr::from_terms(c.wrapping_add(d), terms)
+    }
+}
+
+impl ops::Mul for Expr {
+    type Output = Expr;
+
+    fn mul(self, other: Expr) -> Expr {
+        let (factor, x) = match (self, other) {
+            (Expr::Const(c), x) | (x, Expr::Const(c)) => (c, x),
+            (a, b) => return Expr::Mul(Rc::new(a), Rc::new(b)),
+        };
+        let (c, terms) = x.terms();
+        let terms = terms
+            .into_iter()
+            .map(|(atom, k)| (atom, k.wrapping_mul(factor)))
+            .collect();
+        Expr::from_terms(c.wrapping_mul(factor), terms)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (a, op, b) = match self {
+            Expr::Const(val) => return write!(f, "{}", val),
+            Expr::Sym(name) => return write!(f, "{}", name),
+            Expr::Load(addr) => return write!(f, "[{}]", addr),
+            Expr::Add(a, b) => (a, "+", b),
+            Expr::Mul(a, b) => (a, "*", b),
+            Expr::Lt(a, b) => (a, "<", b),
+            Expr::Eq(a, b) => (a, "==", b),
+        };
+        for (i, operand) in [a, b].iter().enumerate() {
+            if i == 1 {
+                write!(f, " {} ", op)?;
+            }
+            if operand.is_compound() {
+                write!(f, "({})", operand)?;
+            } else {
+                write!(f, "{}", operand)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// A branch decision on a symbolic value: the value was non-zero if holds is set, else zero
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    pub expr: Expr,
+    pub holds: bool,
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.expr, self.holds) {
+            (Expr::Lt(a, b), true) => write!(f, "{} < {}", a, b),
+            (Expr::Lt(a, b), false) => write!(f, "{} >= {}", a, b),
+            (Expr::Eq(a, b), true) => write!(f, "{} == {}", a, b),
+            (Expr::Eq(a, b), false) => write!(f, "{} != {}", a, b),
+            (expr, true) => write!(f, "{} != 0", expr),
+            (expr, false) => write!(f, "{} == 0", expr),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SymbolicError {
+    SymbolicOpcode { ip: usize },
+    SymbolicAddress { ip: usize },
+    SymbolicJump { ip: usize },
+    SymbolicOffset { ip: usize },
+    InvalidOpcode { ip: usize, opcode: i64 },
+    InvalidAddress { ip: usize, addr: i64 },
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymbolicError::SymbolicOpcode { ip } => write!(f, "symbolic instruction at {}", ip),
+            SymbolicError::SymbolicAddress { ip } => write!(f, "symbolic address at {}", ip),
+            SymbolicError::SymbolicJump { ip } => write!(f, "symbolic jump target at {}", ip),
+            SymbolicError::SymbolicOffset { ip } => {
+                write!(f, "symbolic relative base offset at {}", ip)
+            }
+            SymbolicError::InvalidOpcode { ip, opcode } => {
+                write!(f, "invalid opcode {} at {}", opcode, ip)
+            }
+            SymbolicError::InvalidAddress { ip, addr } => {
+                write!(f, "invalid address {} at {}", addr, ip)
+            }
+        }
+    }
+}
+
+impl Error for SymbolicError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum End {
+    Halted,
+    StepLimit,
+    // The path forked when the maximum number of paths was reached
+    PathLimit,
+    Error(SymbolicError),
+}
+
+#[derive(Clone, Debug)]
+pub struct Path {
+    pub conditions: Vec<Condition>,
+    pub outputs: Vec<Expr>,
+    pub memory: Vec<Expr>,
+    pub end: End,
+}
+
+impl Path {
+    // Final contents of the cells that depend on symbols
+    pub fn symbolic_cells(&self) -> impl Iterator<Item = (usize, &Expr)> {
+        self.memory
+            .iter()
+            .enumerate()
+            .filter(|(_, expr)| expr.value().is_none())
+    }
+}
+
+#[derive(Clone)]
+struct State {
+    ip: usize,
+    rbp: i64,
+    memory: Vec<Expr>,
+    input: usize,
+    conditions: Vec<Condition>,
+    outputs: Vec<Expr>,
+    steps: usize,
+}
+
+impl State {
+    fn cell(&mut self, addr: usize) -> &mut Expr {
+        if addr >= self.memory.len() {
+            self.memory.resize(addr + 1, Expr::Const(0));
+        }
+        &mut self.memory[addr]
+    }
+
+    fn address(&mut self, ip: usize, param: usize, mode: Mode) -> Result<usize, SymbolicError> {
+        let raw = self
+            .cell(ip + param)
+            .value()
+            .ok_or(SymbolicError::SymbolicAddress { ip })?;
+        // addresses past the range of i64 are reported saturated
+        let addr = match mode {
+            Mode::Relative => self.rbp.checked_add(raw),
+            _ => Some(raw),
+        };
+        match addr {
+            Some(addr) if addr >= 0 => Ok(addr as usize),
+            _ => Err(SymbolicError::InvalidAddress {
+                ip,
+                addr: addr.unwrap_or_else(|| self.rbp.saturating_add(raw)),
+            }),
+        }
+    }
+
+    fn load(&mut self, ip: usize, param: usize, mode: Mode) -> Result<Expr, SymbolicError> {
+        match mode {
+            Mode::Immediate => Ok(self.cell(ip + param).clone()),
+            Mode::Position if self.cell(ip + param).value().is_none() => {
+                Ok(Expr::Load(Rc::new(self.cell(ip + param).clone())))
+            }
+            _ => {
+                let addr = self.address(ip, param, mode)?;
+                Ok(self.cell(addr).clone())
+            }
+        }
+    }
+
+    fn end(self, end: End) -> Path {
+        Path {
+            conditions: self.conditions,
+            outputs: self.outputs,
+            memory: self.memory,
+            end,
+        }
+    }
+}
+
+enum Step {
+    Continue,
+    Fork(Expr),
+    Halt,
+}
+
+pub struct SymbolicCpu {
+    memory: Vec<Expr>,
+    inputs: Vec<Expr>,
+    max_steps: usize,
+    max_paths: usize,
+}
+
+impl SymbolicCpu {
+    pub fn new(program: &[i64]) -> SymbolicCpu {
+        SymbolicCpu {
+            memory: program.iter().map(|val| Expr::Const(*val)).collect(),
+            inputs: vec![],
+            max_steps: 100_000,
+            max_paths: 256,
+        }
+    }
+
+    pub fn set_cell(&mut self, addr: usize, expr: Expr) {
+        if addr >= self.memory.len() {
+            self.memory.resize(addr + 1, Expr::Const(0));
+        }
+        self.memory[addr] = expr;
+    }
+
+    pub fn memory(&self) -> &[Expr] {
+        &self.memory
+    }
+
+    // Inputs are consumed in order, IN instructions beyond them read fresh symbols in0, in1, ...
+    pub fn push_input(&mut self, expr: Expr) {
+        self.inputs.push(expr);
+    }
+
+    // Limits for the number of instructions per path and the number of paths
+    pub fn set_limits(&mut self, max_steps: usize, max_paths: usize) {
+        self.max_steps = max_steps;
+        self.max_paths = max_paths;
+    }
+
+    // Explores all paths through the program, up to the configured limits
+    pub fn run(&self) -> Vec<Path> {
+        let mut paths = vec![];
+        let mut pending = vec![State {
+            ip: 0,
+            rbp: 0,
+            memory: self.memory.clone(),
+            input: 0,
+            conditions: vec![],
+            outputs: vec![],
+            steps: 0,
+        }];
+        while let Some(mut state) = pending.pop() {
+            loop {
+                if state.steps == self.max_steps {
+                    paths.push(state.end(End::StepLimit));
+                    break;
+                }
+                state.steps += 1;
+                match self.step(&mut state) {
+                    Ok(Step::Continue) => {}
+                    Ok(Step::Halt) => {
+                        paths.push(state.end(End::Halted));
+                        break;
+                    }
+                    Ok(Step::Fork(_)) if paths.len() + pending.len() + 1 >= self.max_paths => {
+                        paths.push(state.end(End::PathLimit));
+                        break;
+                    }
+                    Ok(Step::Fork(cond)) => {
+                        let mut other = state.clone();
+                        self.decide(&mut other, cond.clone(), false);
+                        pending.push(other);
+                        self.decide(&mut state, cond, true);
+                    }
+                    Err(e) => {
+                        paths.push(state.end(End::Error(e)));
+                        break;
+                    }
+                }
+            }
+        }
+        paths
+    }
+
+    // Continues a forked jump as if its condition was non-zero or zero
+    fn decide(&self, state: &mut State, cond: Expr, holds: bool) {
+        let opcode = state.memory[state.ip].value().unwrap() % 100;
+        let jump = (opcode == JNZ) == holds;
+        state.conditions.push(Condition { expr: cond, holds });
+        self.jump(state, jump);
+    }
+
+    fn jump(&self, state: &mut State, jump: bool) {
+        let ip = state.ip;
+        if !jump {
+            state.ip += 3;
+            return;
+        }
+        let modes = state.memory[ip].value().unwrap() / 100;
+        // the target was checked to be concrete before forking
+        state.ip = state
+            .load(ip, 2, Mode::from_digit(modes / 10 % 10))
+            .ok()
+            .and_then(|target| target.value())
+            .unwrap() as usize;
+    }
+
+    fn step(&self, state: &mut State) -> Result<Step, SymbolicError> {
+        let ip = state.ip;
+        let inst = state
+            .cell(ip)
+            .value()
+            .ok_or(SymbolicError::SymbolicOpcode { ip })?;
+        let opcode = inst % 100;
+        let mode = |i: usize| Mode::from_digit(inst / 10i64.pow(i as u32 + 1) % 10);
+        match opcode {
+            ADD | MUL | LT | EQ => {
+                let a = state.load(ip, 1, mode(1))?;
+                let b = state.load(ip, 2, mode(2))?;
+                let dst = state.address(ip, 3, mode(3))?;
+                *state.cell(dst) = match opcode {
+                    ADD => a + b,
+                    MUL => a * b,
+                    LT => Expr::lt(a, b),
+                    _ => Expr::eq(a, b),
+                };
+                state.ip += 4;
+            }
+            IN => {
+                let dst = state.address(ip, 1, mode(1))?;
+                let val = match self.inputs.get(state.input) {
+                    Some(expr) => expr.clone(),
+                    None => Expr::sym(&format!("in{}", state.input)),
+                };
+                state.input += 1;
+                *state.cell(dst) = val;
+                state.ip += 2;
+            }
+            OUT => {
+                let val = state.load(ip, 1, mode(1))?;
+                state.outputs.push(val);
+                state.ip += 2;
+            }
+            JNZ | JZ => {
+                let cond = state.load(ip, 1, mode(1))?;
+                let target = state.load(ip, 2, mode(2))?;
+                if target.value().is_none() {
+                    return Err(SymbolicError::SymbolicJump { ip });
+                }
+                match cond.value() {
+                    Some(val) => self.jump(state, (val != 0) == (opcode == JNZ)),
+                    None => {
+                        // a condition that was decided before on this path is not forked again
+                        let known = state.conditions.iter().find(|c| c.expr == cond);
+                        match known.map(|c| c.holds) {
+                            Some(holds) => self.jump(state, holds == (opcode == JNZ)),
+                            None => return Ok(Step::Fork(cond)),
+                        }
+                    }
+                }
+            }
+            RBO => {
+                let offset = state.load(ip, 1, mode(1))?;
+                let offset = offset.value().ok_or(SymbolicError::SymbolicOffset { ip })?;
+                // a relative base past the range of i64 can not address anything
+                state.rbp = state
+                    .rbp
+                    .checked_add(offset)
+                    .ok_or(SymbolicError::InvalidAddress {
+                        ip,
+                        addr: state.rbp.saturating_add(offset),
+                    })?;
+                state.ip += 2;
+            }
+            HLT => return Ok(Step::Halt),
+            _ => return Err(SymbolicError::InvalidOpcode { ip, opcode }),
+        }
+        Ok(Step::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::IntCodeCpu;
+
+    fn program(code: &str) -> Vec<i64> {
+        IntCodeCpu::from_code(code).memory().to_vec()
+    }
+
+    #[test]
+    fn test_straight_line() {
+        // [3] = [noun] + [verb], [3] = noun + verb, [0] = [3] * 3, [0] = [0] + noun
+        let code = "1,0,0,3,1,1,2,3,1002,3,3,0,1,0,1,0,99";
+        let mut cpu = SymbolicCpu::new(&program(code));
+        cpu.set_cell(1, Expr::sym("noun"));
+        cpu.set_cell(2, Expr::sym("verb"));
+        let paths = cpu.run();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].end, End::Halted);
+        let result = &paths[0].memory[0];
+        assert_eq!(result.to_string(), "(noun * 4) + (verb * 3)");
+        assert_eq!(
+            (Expr::Load(Rc::new(Expr::sym("noun"))) + Expr::Const(1)).to_string(),
+            "[noun] + 1"
+        );
+
+        let (c, coeffs) = result.linear().unwrap();
+        assert_eq!(c, 0);
+        assert_eq!(coeffs["noun"], 4);
+        assert_eq!(coeffs["verb"], 3);
+        let values = vec![("noun".to_string(), 2), ("verb".to_string(), 5)];
+        assert_eq!(result.eval(&values.into_iter().collect()), Some(23));
+    }
+
+    #[test]
+    fn test_folding() {
+        let x = Expr::sym("x");
+        let e = x.clone() + Expr::Const(2) + Expr::Const(3);
+        assert_eq!(e.to_string(), "x + 5");
+        let e = Expr::Const(2) * (x.clone() * Expr::Const(4));
+        assert_eq!(e.to_string(), "x * 8");
+        assert_eq!(x.clone() * Expr::Const(0), Expr::Const(0));
+        let e = x.clone() + Expr::Const(1) + x.clone() * Expr::Const(-1);
+        assert_eq!(e, Expr::Const(1));
+        let y = Expr::sym("y");
+        let e = (x.clone() + y.clone()) * (y + Expr::Const(2));
+        assert_eq!(e.to_string(), "(x + y) * (y + 2)");
+        assert_eq!(Expr::eq(x.clone(), x.clone()), Expr::Const(1));
+
+        // linear wraps around like the cpu does
+        let big = Expr::Mul(Rc::new(x), Rc::new(Expr::Const(i64::MAX)));
+        let e = Expr::Add(Rc::new(big), Rc::new(Expr::Const(i64::MAX)));
+        let e = Expr::Mul(Rc::new(e), Rc::new(Expr::Const(2)));
+        let (c, coeffs) = e.linear().unwrap();
+        assert_eq!((c, coeffs["x"]), (-2, -2));
+    }
+
+    #[test]
+    fn test_branches() {
+        // outputs 1 if the input is less than 8, else 0
+        let paths = SymbolicCpu::new(&program("3,9,7,9,10,9,4,9,99,-1,8")).run();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].outputs[0].to_string(), "in0 < 8");
+
+        // outputs 0 if the input is zero, else 1
+        let code = "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9";
+        let paths = SymbolicCpu::new(&program(code)).run();
+        assert_eq!(paths.len(), 2);
+        let taken = paths.iter().find(|p| p.conditions[0].holds).unwrap();
+        assert_eq!(taken.conditions[0].to_string(), "in0 != 0");
+        assert_eq!(taken.outputs, vec![Expr::Const(1)]);
+        let not_taken = paths.iter().find(|p| !p.conditions[0].holds).unwrap();
+        assert_eq!(not_taken.conditions[0].to_string(), "in0 == 0");
+        assert_eq!(not_taken.outputs, vec![Expr::Const(0)]);
+    }
+
+    #[test]
+    fn test_limits() {
+        // counts down from the input, loops forever on negative input
+        let code = "3,10,1001,10,-1,10,1005,10,2,99,0";
+        let mut cpu = SymbolicCpu::new(&program(code));
+        cpu.set_limits(1000, 4);
+        let paths = cpu.run();
+        assert_eq!(paths.len(), 4);
+        assert_eq!(paths.iter().filter(|p| p.end == End::PathLimit).count(), 1);
+        assert!(paths.iter().any(|p| p.end == End::Halted));
+
+        let mut cpu = SymbolicCpu::new(&program("3,4,1105,1,0,99"));
+        cpu.push_input(Expr::sym("target"));
+        let paths = cpu.run();
+        assert_eq!(
+            paths[0].end,
+            End::Error(SymbolicError::SymbolicJump { ip: 2 })
+        );
+
+        // relative bases and addresses past the range of i64
+        let paths = SymbolicCpu::new(&program("109,9223372036854775807,204,1,99")).run();
+        assert_eq!(
+            paths[0].end,
+            End::Error(SymbolicError::InvalidAddress {
+                ip: 2,
+                addr: i64::MAX
+            })
+        );
+        let paths = SymbolicCpu::new(&program("109,-9223372036854775808,109,-1,99")).run();
+        assert_eq!(
+            paths[0].end,
+            End::Error(SymbolicError::InvalidAddress {
+                ip: 2,
+                addr: i64::MIN
+            })
+        );
+    }
+}
@@ -1 +1,8 @@
+pub mod bitmap;
+pub mod fuel;
 pub mod intcode;
+pub mod ocr;
+pub mod orbits;
+pub mod passwords;
+pub mod sif;
+pub mod wires;
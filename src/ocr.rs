@@ -0,0 +1,172 @@
+use std::error::Error;
+use std::fmt;
+
+// Reads the capital letters the puzzles draw: glyphs 4 pixels wide and 6 pixels tall, with one
+// blank column between letters. Only the letters seen in puzzle answers are known.
+
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+
+const FONT: &[(char, [&str; GLYPH_HEIGHT])] = &[
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum OcrError {
+    // Nothing is set in the grid
+    Empty,
+    // The rows between the first and the last set pixel
+    Height { rows: usize },
+    // The text read so far with ? for every unknown glyph, and the grid columns where the
+    // unknown glyphs start
+    UnknownGlyphs { text: String, columns: Vec<usize> },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::Empty => write!(f, "no letters in the image"),
+            OcrError::Height { rows } => write!(
+                f,
+                "letters are {} pixels tall, expected {}",
+                rows, GLYPH_HEIGHT
+            ),
+            OcrError::UnknownGlyphs { text, columns } => {
+                let columns: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+                write!(
+                    f,
+                    "unknown glyphs at columns {} in {:?}",
+                    columns.join(", "),
+                    text
+                )
+            }
+        }
+    }
+}
+
+impl Error for OcrError {}
+
+// Reads the letters from a grid of rows, true for set pixels. Blank rows and columns around the
+// letters are ignored.
+pub fn recognize(grid: &[Vec<bool>]) -> Result<String, OcrError> {
+    let set_rows: Vec<usize> = (0..grid.len())
+        .filter(|y| grid[*y].iter().any(|p| *p))
+        .collect();
+    let (top, bottom) = match (set_rows.first(), set_rows.last()) {
+        (Some(top), Some(bottom)) => (*top, *bottom),
+        _ => return Err(OcrError::Empty),
+    };
+    if bottom - top + 1 != GLYPH_HEIGHT {
+        return Err(OcrError::Height {
+            rows: bottom - top + 1,
+        });
+    }
+    let rows = &grid[top..=bottom];
+    let pixel = |x: usize, y: usize| rows[y].get(x).cloned().unwrap_or(false);
+    let width = rows.iter().map(|r| r.len()).max().unwrap();
+    let column_set = |x: usize| (0..GLYPH_HEIGHT).any(|y| pixel(x, y));
+    let left = (0..width).find(|x| column_set(*x)).unwrap();
+    let right = (0..width).rev().find(|x| column_set(*x)).unwrap();
+
+    let mut text = String::new();
+    let mut unknown = vec![];
+    for column in (left..=right).step_by(GLYPH_WIDTH + 1) {
+        let glyph = FONT.iter().find(|(_, pattern)| {
+            pattern.iter().enumerate().all(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .all(|(x, c)| (c == '#') == pixel(column + x, y))
+            })
+        });
+        match glyph {
+            Some((letter, _)) => text.push(*letter),
+            None => {
+                text.push('?');
+                unknown.push(column);
+            }
+        }
+    }
+
+    if unknown.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::UnknownGlyphs {
+            text,
+            columns: unknown,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(art: &str, set: char) -> Vec<Vec<bool>> {
+        art.lines()
+            .map(|line| line.chars().map(|c| c == set).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_font() {
+        for (letter, pattern) in FONT {
+            assert_eq!(
+                recognize(&grid(&pattern.join("\n"), '#')),
+                Ok(letter.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_puzzle_output() {
+        assert_eq!(
+            recognize(&grid(include_str!("../output/day08.out"), '█')),
+            Ok("UBUFP".to_string())
+        );
+        assert_eq!(
+            recognize(&grid(include_str!("../output/day11.out"), '█')),
+            Ok("JUFEKHPH".to_string())
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(recognize(&grid("....\n....\n", '#')), Err(OcrError::Empty));
+        assert_eq!(
+            recognize(&grid("#..#\n#..#\n####\n", '#')),
+            Err(OcrError::Height { rows: 3 })
+        );
+        let art = "\n\
+                   ..#..#.####\n\
+                   ..#..#.#...\n\
+                   ..####.###.\n\
+                   ..#..#.#...\n\
+                   ..#..#.#...\n\
+                   ..#..#.#.#.\n\
+                   \n";
+        let error = recognize(&grid(art, '#')).unwrap_err();
+        assert_eq!(
+            error,
+            OcrError::UnknownGlyphs {
+                text: "H?".to_string(),
+                columns: vec![7]
+            }
+        );
+        assert_eq!(error.to_string(), "unknown glyphs at columns 7 in \"H?\"");
+    }
+}
@@ -0,0 +1,609 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+#[derive(Debug, PartialEq)]
+pub enum OrbitError {
+    // A line that is not of the form A)B, lines count from 1
+    InvalidLine {
+        line: usize,
+        text: String,
+    },
+    // An object that directly orbits more than one object, in input order
+    MultipleParents {
+        object: String,
+        parents: Vec<String>,
+    },
+    // Objects that orbit each other in a loop, each orbiting the next and the last the first
+    Cycle {
+        members: Vec<String>,
+    },
+    // The map falls apart into several pieces, named by their root or a cycle member
+    Disconnected {
+        components: Vec<String>,
+    },
+    MissingObject {
+        object: String,
+    },
+    // The object is in the map but does not orbit the root
+    Unreachable {
+        object: String,
+        root: String,
+    },
+}
+
+impl fmt::Display for OrbitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrbitError::InvalidLine { line, text } => {
+                write!(f, "invalid orbit {:?} in line {}", text, line)
+            }
+            OrbitError::MultipleParents { object, parents } => {
+                write!(f, "{} orbits {}", object, parents.join(" and "))
+            }
+            OrbitError::Cycle { members } => {
+                write!(f, "orbit cycle {} -> {}", members.join(" -> "), members[0])
+            }
+            OrbitError::Disconnected { components } => write!(
+                f,
+                "map has {} separate components at {}",
+                components.len(),
+                components.join(", ")
+            ),
+            OrbitError::MissingObject { object } => write!(f, "{} is not in the map", object),
+            OrbitError::Unreachable { object, root } => {
+                write!(f, "{} does not orbit {}", object, root)
+            }
+        }
+    }
+}
+
+impl Error for OrbitError {}
+
+// Everything wrong with an orbit map, in the order the checks run
+#[derive(Debug, PartialEq)]
+pub struct Diagnostics {
+    pub errors: Vec<OrbitError>,
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for Diagnostics {}
+
+// Parses and checks a map: every line must be an orbit, every object orbits at most one object,
+// there are no cycles, everything hangs off the root, and the required objects orbit the root.
+// All problems found are reported together.
+pub fn validate(input: &str, root: &str, required: &[&str]) -> Result<OrbitTree, Diagnostics> {
+    let mut errors = vec![];
+    let mut edges = vec![];
+    for (i, line) in input.lines().enumerate() {
+        match parse_edges(line) {
+            Ok(parsed) => edges.extend(parsed),
+            Err(OrbitError::InvalidLine { text, .. }) => {
+                errors.push(OrbitError::InvalidLine { line: i + 1, text })
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    let mut parents: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut order = vec![];
+    for (orbited, orbiter) in &edges {
+        let list = parents.entry(orbiter).or_insert_with(|| {
+            order.push(*orbiter);
+            vec![]
+        });
+        if !list.contains(orbited) {
+            list.push(orbited);
+        }
+    }
+    for object in order {
+        if parents[object].len() > 1 {
+            errors.push(OrbitError::MultipleParents {
+                object: object.to_string(),
+                parents: parents[object].iter().map(|p| p.to_string()).collect(),
+            });
+        }
+    }
+
+    let tree = OrbitTree::new(&edges);
+    let cycles = tree.cycles();
+    let mut components: Vec<String> = (0..tree.len())
+        .filter(|i| tree.parent[*i].is_none())
+        .map(|i| tree.names[i].clone())
+        .collect();
+    for members in &cycles {
+        components.push(members[0].clone());
+        errors.push(OrbitError::Cycle {
+            members: members.clone(),
+        });
+    }
+    if components.len() > 1 {
+        errors.push(OrbitError::Disconnected { components });
+    }
+
+    for object in std::iter::once(&root).chain(required) {
+        if !tree.contains(object) {
+            errors.push(OrbitError::MissingObject {
+                object: object.to_string(),
+            });
+        } else if tree.contains(root) && !tree.orbits_root(object, root) {
+            errors.push(OrbitError::Unreachable {
+                object: object.to_string(),
+                root: root.to_string(),
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(tree)
+    } else {
+        Err(Diagnostics { errors })
+    }
+}
+
+// Parses one A)B edge per line, B orbits A. Empty lines are skipped.
+pub fn parse_edges(input: &str) -> Result<Vec<(&str, &str)>, OrbitError> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let mut parts = line.trim().splitn(2, ')');
+            match (parts.next(), parts.next()) {
+                (Some(a), Some(b)) if !a.is_empty() && !b.is_empty() && !b.contains(')') => {
+                    Ok((a, b))
+                }
+                _ => Err(OrbitError::InvalidLine {
+                    line: i + 1,
+                    text: line.to_string(),
+                }),
+            }
+        })
+        .collect()
+}
+
+// The objects and who orbits whom, with the depth of every object and ancestor tables for
+// lowest common ancestor queries: up[k][i] is the 2^k-th ancestor of object i, roots are their
+// own ancestors.
+#[derive(Clone, Debug)]
+pub struct OrbitTree {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    parent: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    depth: Vec<usize>,
+    up: Vec<Vec<usize>>,
+}
+
+impl OrbitTree {
+    // Builds the tree from (orbited, orbiter) edges. If an object has several parents the last
+    // one wins. Objects that are not reachable from a root, i.e. on cycles, get depth 0.
+    pub fn new(edges: &[(&str, &str)]) -> OrbitTree {
+        let mut tree = OrbitTree {
+            names: vec![],
+            ids: HashMap::new(),
+            parent: vec![],
+            children: vec![],
+            depth: vec![],
+            up: vec![],
+        };
+        for (orbited, orbiter) in edges {
+            let a = tree.intern(orbited);
+            let b = tree.intern(orbiter);
+            tree.parent[b] = Some(a);
+        }
+        for (b, parent) in tree.parent.iter().enumerate() {
+            if let Some(a) = parent {
+                tree.children[*a].push(b);
+            }
+        }
+
+        let mut order: Vec<usize> = (0..tree.len())
+            .filter(|i| tree.parent[*i].is_none())
+            .collect();
+        let mut i = 0;
+        while i < order.len() {
+            let node = order[i];
+            for &child in &tree.children[node] {
+                tree.depth[child] = tree.depth[node] + 1;
+                order.push(child);
+            }
+            i += 1;
+        }
+
+        let levels = (usize::BITS - tree.len().leading_zeros()).max(1) as usize;
+        let first: Vec<usize> = (0..tree.len())
+            .map(|i| tree.parent[i].unwrap_or(i))
+            .collect();
+        tree.up.push(first);
+        for k in 1..levels {
+            let prev = &tree.up[k - 1];
+            let next = (0..tree.len()).map(|i| prev[prev[i]]).collect();
+            tree.up.push(next);
+        }
+        tree
+    }
+
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.parent.push(None);
+        self.children.push(vec![]);
+        self.depth.push(0);
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.ids.contains_key(name)
+    }
+
+    pub fn parent(&self, name: &str) -> Option<&str> {
+        let id = *self.ids.get(name)?;
+        self.parent[id].map(|p| self.names[p].as_str())
+    }
+
+    // Number of direct and indirect orbits of the object
+    pub fn depth(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).map(|id| self.depth[*id])
+    }
+
+    // Sum of direct and indirect orbits over all objects
+    pub fn total_orbits(&self) -> usize {
+        self.depth.iter().sum()
+    }
+
+    // Objects on orbit cycles, one list per cycle in orbit order
+    fn cycles(&self) -> Vec<Vec<String>> {
+        // walk[i] is the walk that first visited object i, walks are numbered from 1
+        let mut walk = vec![0; self.len()];
+        let mut cycles = vec![];
+        for start in 0..self.len() {
+            let id = start + 1;
+            let mut node = start;
+            while walk[node] == 0 {
+                walk[node] = id;
+                match self.parent[node] {
+                    Some(parent) => node = parent,
+                    None => break,
+                }
+            }
+            if walk[node] == id && self.parent[node].is_some() {
+                let mut members = vec![self.names[node].clone()];
+                let mut next = self.parent[node].unwrap();
+                while next != node {
+                    members.push(self.names[next].clone());
+                    next = self.parent[next].unwrap();
+                }
+                cycles.push(members);
+            }
+        }
+        cycles
+    }
+
+    // Whether the object is the root or orbits it directly or indirectly
+    fn orbits_root(&self, object: &str, root: &str) -> bool {
+        let (object, root) = (self.ids[object], self.ids[root]);
+        if self.depth[object] < self.depth[root] || self.cycles_through(object) {
+            return false;
+        }
+        self.ancestor(object, self.depth[object] - self.depth[root]) == root
+    }
+
+    // Objects below a cycle are never reached from a root and keep depth 0
+    fn cycles_through(&self, object: usize) -> bool {
+        let top = self.ancestor(object, self.depth[object]);
+        self.parent[top].is_some()
+    }
+
+    fn ancestor(&self, mut node: usize, steps: usize) -> usize {
+        for (k, up) in self.up.iter().enumerate() {
+            if steps & (1 << k) != 0 {
+                node = up[node];
+            }
+        }
+        node
+    }
+
+    fn lca_id(&self, a: usize, b: usize) -> Option<usize> {
+        let (mut a, mut b) = if self.depth[a] >= self.depth[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        a = self.ancestor(a, self.depth[a] - self.depth[b]);
+        if a == b {
+            return Some(a);
+        }
+        for up in self.up.iter().rev() {
+            if up[a] != up[b] {
+                a = up[a];
+                b = up[b];
+            }
+        }
+        // objects in different trees have no common ancestor
+        Some(self.up[0][a]).filter(|p| *p == self.up[0][b] && self.parent[a].is_some())
+    }
+
+    // The deepest object that both objects orbit directly or indirectly, or one of them
+    pub fn lca(&self, a: &str, b: &str) -> Option<&str> {
+        let lca = self.lca_id(*self.ids.get(a)?, *self.ids.get(b)?)?;
+        Some(&self.names[lca])
+    }
+
+    // Number of orbit edges between the two objects
+    pub fn distance(&self, a: &str, b: &str) -> Option<usize> {
+        let (a, b) = (*self.ids.get(a)?, *self.ids.get(b)?);
+        let lca = self.lca_id(a, b)?;
+        Some(self.depth[a] + self.depth[b] - 2 * self.depth[lca])
+    }
+
+    // Orbital transfers needed to move from the object a orbits to the object b orbits
+    pub fn transfers(&self, a: &str, b: &str) -> Option<usize> {
+        self.distance(self.parent(a)?, self.parent(b)?)
+    }
+
+    // The objects on the way from a to b, both included
+    pub fn path(&self, a: &str, b: &str) -> Option<Vec<&str>> {
+        let (a, b) = (*self.ids.get(a)?, *self.ids.get(b)?);
+        let lca = self.lca_id(a, b)?;
+        let chain = |mut node: usize| {
+            let mut chain = vec![];
+            while node != lca {
+                chain.push(self.names[node].as_str());
+                node = self.parent[node].unwrap();
+            }
+            chain
+        };
+        let mut path = chain(a);
+        path.push(&self.names[lca]);
+        let mut back = chain(b);
+        back.reverse();
+        path.extend(back);
+        Some(path)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DotOptions<'a> {
+    // Draws the path between the two objects in red
+    pub highlight: Option<(&'a str, &'a str)>,
+    // Replaces runs of objects with exactly one orbiter by a single edge labelled with its length
+    pub collapse: bool,
+}
+
+// Writes the map as a Graphviz digraph with edges from the orbited object to the orbiter.
+// Objects that are not reachable from a root, i.e. on or below cycles, are left out.
+pub fn write_dot<W: Write>(tree: &OrbitTree, options: &DotOptions, out: &mut W) -> io::Result<()> {
+    let mut on_path = HashSet::new();
+    let mut lca = None;
+    let mut endpoints = vec![];
+    if let Some((a, b)) = options.highlight {
+        if let Some(path) = tree.path(a, b) {
+            on_path.extend(path.iter().map(|name| tree.ids[*name]));
+            lca = tree.lca(a, b).map(|name| tree.ids[name]);
+            endpoints = vec![tree.ids[a], tree.ids[b]];
+        }
+    }
+    // an edge ending at a path object is on the path unless it ends at the top of the path
+    let highlighted = |node: usize| on_path.contains(&node) && Some(node) != lca;
+    let hidden = |node: usize| {
+        options.collapse && tree.children[node].len() == 1 && !endpoints.contains(&node)
+    };
+
+    writeln!(out, "digraph orbits {{")?;
+    writeln!(out, "  node [shape=ellipse];")?;
+    let mut stack: Vec<usize> = (0..tree.len())
+        .filter(|i| tree.parent[*i].is_none())
+        .rev()
+        .collect();
+    while let Some(node) = stack.pop() {
+        if on_path.contains(&node) {
+            writeln!(
+                out,
+                "  {:?} [style=filled, fillcolor=red, fontcolor=white];",
+                tree.names[node]
+            )?;
+        } else if tree.parent[node].is_none() || tree.children[node].is_empty() {
+            writeln!(out, "  {:?};", tree.names[node])?;
+        }
+        for &child in &tree.children[node] {
+            let mut end = child;
+            let mut length = 1;
+            while hidden(end) {
+                end = tree.children[end][0];
+                length += 1;
+            }
+            let mut attributes = vec![];
+            if length > 1 {
+                attributes.push(format!("label=\"{}\"", length));
+            }
+            if highlighted(end) {
+                attributes.push("color=red, penwidth=3".to_string());
+            }
+            write!(out, "  {:?} -> {:?}", tree.names[node], tree.names[end])?;
+            if !attributes.is_empty() {
+                write!(out, " [{}]", attributes.join(", "))?;
+            }
+            writeln!(out, ";")?;
+            stack.push(end);
+        }
+    }
+    writeln!(out, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN\n";
+
+    #[test]
+    fn test_orbits() {
+        let tree = OrbitTree::new(&parse_edges(EXAMPLE).unwrap());
+        assert_eq!(tree.len(), 14);
+        assert_eq!(tree.depth("L"), Some(7));
+        // the example without YOU and SAN has 42 orbits
+        assert_eq!(tree.total_orbits() - 7 - 5, 42);
+        assert_eq!(tree.transfers("YOU", "SAN"), Some(4));
+        assert_eq!(tree.lca("YOU", "SAN"), Some("D"));
+        assert_eq!(tree.lca("L", "K"), Some("K"));
+        assert_eq!(tree.distance("H", "F"), Some(6));
+        assert_eq!(tree.distance("COM", "COM"), Some(0));
+        assert_eq!(
+            tree.path("YOU", "SAN"),
+            Some(vec!["YOU", "K", "J", "E", "D", "I", "SAN"])
+        );
+        assert_eq!(tree.path("C", "E"), Some(vec!["C", "D", "E"]));
+        assert_eq!(tree.transfers("COM", "SAN"), None);
+        assert_eq!(tree.distance("YOU", "X"), None);
+    }
+
+    #[test]
+    fn test_forest() {
+        let tree = OrbitTree::new(&parse_edges("A)B\nB)C\nX)Y\n").unwrap());
+        assert_eq!(tree.lca("C", "Y"), None);
+        assert_eq!(tree.lca("A", "X"), None);
+        assert_eq!(tree.distance("A", "C"), Some(2));
+    }
+
+    #[test]
+    fn test_deep_chain() {
+        let names: Vec<String> = (0..5000).map(|i| format!("N{}", i)).collect();
+        let edges: Vec<(&str, &str)> = names
+            .windows(2)
+            .map(|w| (w[0].as_str(), w[1].as_str()))
+            .collect();
+        let tree = OrbitTree::new(&edges);
+        assert_eq!(tree.total_orbits(), 4999 * 5000 / 2);
+        assert_eq!(tree.distance("N17", "N4321"), Some(4304));
+    }
+
+    #[test]
+    fn test_validate() {
+        let tree = validate(EXAMPLE, "COM", &["YOU", "SAN"]).unwrap();
+        assert_eq!(tree.transfers("YOU", "SAN"), Some(4));
+        assert_eq!(
+            validate(EXAMPLE, "COM", &["YOU", "X"]).unwrap_err().errors,
+            vec![OrbitError::MissingObject {
+                object: "X".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let input = "COM)B\nB)C\nA)C\nCD\nX)Y\nY)Z\nZ)X\nZ)YOU\nP)SAN\n";
+        let errors = validate(input, "COM", &["YOU", "SAN"]).unwrap_err().errors;
+        let strings = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                OrbitError::InvalidLine {
+                    line: 4,
+                    text: "CD".to_string()
+                },
+                OrbitError::MultipleParents {
+                    object: "C".to_string(),
+                    parents: strings(&["B", "A"])
+                },
+                OrbitError::Cycle {
+                    members: strings(&["X", "Z", "Y"])
+                },
+                OrbitError::Disconnected {
+                    components: strings(&["COM", "A", "P", "X"])
+                },
+                OrbitError::Unreachable {
+                    object: "YOU".to_string(),
+                    root: "COM".to_string()
+                },
+                OrbitError::Unreachable {
+                    object: "SAN".to_string(),
+                    root: "COM".to_string()
+                },
+            ]
+        );
+        assert_eq!(errors[2].to_string(), "orbit cycle X -> Z -> Y -> X");
+    }
+
+    #[test]
+    fn test_missing_root() {
+        let errors = validate("B)C\n", "COM", &["C"]).unwrap_err().errors;
+        assert_eq!(
+            errors,
+            vec![OrbitError::MissingObject {
+                object: "COM".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_dot() {
+        let tree = OrbitTree::new(&parse_edges(EXAMPLE).unwrap());
+        let mut dot = vec![];
+        write_dot(&tree, &DotOptions::default(), &mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph orbits {\n"));
+        assert_eq!(dot.matches(" -> ").count(), 13);
+        assert!(dot.contains("  \"COM\" -> \"B\";\n"));
+
+        let options = DotOptions {
+            highlight: Some(("YOU", "SAN")),
+            collapse: true,
+        };
+        let mut dot = vec![];
+        write_dot(&tree, &options, &mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        let edges: Vec<&str> = dot.lines().filter(|l| l.contains(" -> ")).collect();
+        assert_eq!(
+            edges,
+            vec![
+                "  \"COM\" -> \"B\";",
+                "  \"B\" -> \"D\" [label=\"2\"];",
+                "  \"B\" -> \"H\" [label=\"2\"];",
+                "  \"D\" -> \"E\" [color=red, penwidth=3];",
+                "  \"D\" -> \"SAN\" [label=\"2\", color=red, penwidth=3];",
+                "  \"E\" -> \"F\";",
+                "  \"E\" -> \"K\" [label=\"2\", color=red, penwidth=3];",
+                "  \"K\" -> \"L\";",
+                "  \"K\" -> \"YOU\" [color=red, penwidth=3];",
+            ]
+        );
+        assert!(dot.contains("  \"YOU\" [style=filled, fillcolor=red, fontcolor=white];"));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        assert_eq!(
+            parse_edges("COM)B\nBC\n"),
+            Err(OrbitError::InvalidLine {
+                line: 2,
+                text: "BC".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+// Password rules for day 4. Valid passwords in a range are counted with a DP over the digits
+// instead of checking every number, so ranges of up to 18 digits stay cheap.
+
+// Prefix states keep one bit per run rule
+pub const MAX_RUNS: usize = u32::BITS as usize;
+
+// Requirement on the runs of equal digits: at least one run has to be of the given length
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Run {
+    AtLeast(usize),
+    Exactly(usize),
+}
+
+impl Run {
+    fn accepts(self, len: usize) -> bool {
+        match self {
+            Run::AtLeast(n) => len >= n,
+            Run::Exactly(n) => len == n,
+        }
+    }
+
+    // Runs longer than this do not need to be told apart
+    fn cap(self) -> usize {
+        match self {
+            Run::AtLeast(n) | Run::Exactly(n) => n + 1,
+        }
+    }
+}
+
+impl fmt::Display for Run {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Run::AtLeast(n) => write!(f, "at least {}", n),
+            Run::Exactly(n) => write!(f, "exactly {}", n),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rejection {
+    DigitCount { expected: usize, found: usize },
+    ForbiddenDigit(u64),
+    // The digit at the position (from 0) is smaller than the one before
+    Decreasing(usize),
+    MissingRun(Run),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rejection::DigitCount { expected, found } => {
+                write!(f, "has {} digits instead of {}", found, expected)
+            }
+            Rejection::ForbiddenDigit(d) => write!(f, "contains forbidden digit {}", d),
+            Rejection::Decreasing(pos) => write!(f, "digit {} decreases", pos + 1),
+            Rejection::MissingRun(run) => write!(f, "has no run of {} equal digits", run),
+        }
+    }
+}
+
+// A set of password rules, built like Rules::new().non_decreasing().run(Run::Exactly(2)) or
+// parsed from a spec like "digits=6,non-decreasing,run=2,run>=3,forbid=05"
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rules {
+    digits: Option<usize>,
+    // bit d is set if digit d must not occur
+    forbidden: u16,
+    non_decreasing: bool,
+    runs: Vec<Run>,
+}
+
+// State of a digit prefix: last digit, length of the current run capped by the rules and
+// one bit per run rule that a finished run already satisfied
+type Prefix = (u64, usize, u32);
+
+impl Rules {
+    pub fn new() -> Rules {
+        Rules::default()
+    }
+
+    pub fn part1() -> Rules {
+        Rules::new().non_decreasing().run(Run::AtLeast(2))
+    }
+
+    pub fn part2() -> Rules {
+        Rules::new().non_decreasing().run(Run::Exactly(2))
+    }
+
+    pub fn digits(mut self, count: usize) -> Rules {
+        self.digits = Some(count);
+        self
+    }
+
+    pub fn forbid(mut self, digits: &[u64]) -> Rules {
+        for d in digits {
+            self.forbidden |= 1 << d;
+        }
+        self
+    }
+
+    pub fn non_decreasing(mut self) -> Rules {
+        self.non_decreasing = true;
+        self
+    }
+
+    // Requires at least one run of equal digits of the given length, every run rule separately.
+    // Panics if there are more than MAX_RUNS run rules.
+    pub fn run(mut self, run: Run) -> Rules {
+        assert!(
+            self.runs.len() < MAX_RUNS,
+            "more than {} run rules",
+            MAX_RUNS
+        );
+        self.runs.push(run);
+        self
+    }
+
+    pub fn check(&self, password: u64) -> Result<(), Rejection> {
+        let digits: Vec<u64> = password
+            .to_string()
+            .bytes()
+            .map(|b| (b - b'0') as u64)
+            .collect();
+        if let Some(expected) = self.digits.filter(|n| *n != digits.len()) {
+            return Err(Rejection::DigitCount {
+                expected,
+                found: digits.len(),
+            });
+        }
+        if let Some(d) = digits.iter().find(|d| self.forbidden & (1 << **d) != 0) {
+            return Err(Rejection::ForbiddenDigit(*d));
+        }
+        if self.non_decreasing {
+            if let Some(pos) = (1..digits.len()).find(|i| digits[*i] < digits[i - 1]) {
+                return Err(Rejection::Decreasing(pos));
+            }
+        }
+        let mut prefix = (0, 0, 0);
+        for d in digits {
+            prefix = self.extend(prefix, d);
+        }
+        let found = self.finish(prefix);
+        match self
+            .runs
+            .iter()
+            .enumerate()
+            .find(|(i, _)| found & (1 << i) == 0)
+        {
+            Some((_, run)) => Err(Rejection::MissingRun(*run)),
+            None => Ok(()),
+        }
+    }
+
+    fn extend(&self, prefix: Prefix, d: u64) -> Prefix {
+        let (last, len, found) = prefix;
+        let cap = self.runs.iter().map(|run| run.cap()).max().unwrap_or(1);
+        if len > 0 && d == last {
+            (d, (len + 1).min(cap), found)
+        } else {
+            (d, 1, self.finish(prefix))
+        }
+    }
+
+    // Marks the run rules that the current run satisfies
+    fn finish(&self, (_, len, found): Prefix) -> u32 {
+        self.runs
+            .iter()
+            .enumerate()
+            .filter(|(_, run)| len > 0 && run.accepts(len))
+            .fold(found, |found, (i, _)| found | 1 << i)
+    }
+
+    // Digits that may follow the prefix, the first one at position 0
+    fn next_digits(&self, prefix: Prefix, first: bool) -> impl Iterator<Item = u64> + '_ {
+        let lowest = match (first, self.non_decreasing) {
+            (true, _) => 1,
+            (false, true) => prefix.0,
+            (false, false) => 0,
+        };
+        (lowest..=9).filter(move |d| self.forbidden & (1 << d) == 0)
+    }
+
+    // Counts valid numbers with exactly as many digits as bound that are not larger than it.
+    // Numbers with the same prefix state are counted together, so this takes
+    // O(digits * states * 10) steps.
+    fn count_up_to(&self, bound: u64) -> u64 {
+        let digits: Vec<u64> = bound
+            .to_string()
+            .bytes()
+            .map(|b| (b - b'0') as u64)
+            .collect();
+        let all = (0..self.runs.len()).fold(0, |all, i| all | 1 << i);
+        // prefixes below the bound, and the prefix equal to it while it is valid
+        let mut below: HashMap<Prefix, u64> = HashMap::new();
+        let mut tight = Some((0, 0, 0));
+        for (i, &limit) in digits.iter().enumerate() {
+            let mut next = HashMap::new();
+            for (&prefix, &count) in &below {
+                for d in self.next_digits(prefix, i == 0) {
+                    *next.entry(self.extend(prefix, d)).or_insert(0) += count;
+                }
+            }
+            if let Some(prefix) = tight {
+                let mut allowed = self.next_digits(prefix, i == 0).peekable();
+                while let Some(d) = allowed.next_if(|d| *d < limit) {
+                    *next.entry(self.extend(prefix, d)).or_insert(0) += 1;
+                }
+                tight = allowed
+                    .next()
+                    .filter(|d| *d == limit)
+                    .map(|d| self.extend(prefix, d));
+            }
+            below = next;
+        }
+        let done = |prefix: Prefix| self.finish(prefix) == all;
+        let below: u64 = below
+            .into_iter()
+            .filter(|(prefix, _)| done(*prefix))
+            .map(|(_, count)| count)
+            .sum();
+        below + tight.filter(|prefix| done(*prefix)).is_some() as u64
+    }
+
+    // Counts valid passwords in lo..=hi, for numbers of up to 18 digits
+    pub fn count(&self, lo: u64, hi: u64) -> u64 {
+        // the digit DP never puts a 0 first, so 0 itself is checked on its own
+        let mut count = (lo == 0 && self.check(0).is_ok()) as u64;
+        let mut start = 10;
+        for len in 1..=18 {
+            // the numbers with this many digits are start / 10..start
+            let (a, b) = (lo.max(start / 10), hi.min(start - 1));
+            if a <= b && self.digits.map_or(true, |n| n == len) {
+                count += self.count_up_to(b);
+                if a > start / 10 {
+                    count -= self.count_up_to(a - 1);
+                }
+            }
+            start *= 10;
+        }
+        count
+    }
+}
+
+impl FromStr for Rules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rules, String> {
+        let mut rules = Rules::new();
+        for item in s.split(',').map(str::trim) {
+            let number = |prefix: &str| item[prefix.len()..].parse::<usize>().ok();
+            rules = match item {
+                "non-decreasing" => rules.non_decreasing(),
+                _ if item.starts_with("digits=") => {
+                    rules.digits(number("digits=").ok_or(format!("invalid digit count: {}", item))?)
+                }
+                _ if item.starts_with("run") && rules.runs.len() == MAX_RUNS => {
+                    return Err(format!("more than {} run rules", MAX_RUNS))
+                }
+                _ if item.starts_with("run>=") => rules.run(Run::AtLeast(
+                    number("run>=").ok_or(format!("invalid run length: {}", item))?,
+                )),
+                _ if item.starts_with("run=") => rules.run(Run::Exactly(
+                    number("run=").ok_or(format!("invalid run length: {}", item))?,
+                )),
+                _ if item.starts_with("forbid=") => {
+                    let digits: Option<Vec<u64>> = item["forbid=".len()..]
+                        .chars()
+                        .map(|c| c.to_digit(10).map(u64::from))
+                        .collect();
+                    rules.forbid(&digits.ok_or(format!("invalid digits: {}", item))?)
+                }
+                _ => return Err(format!("unknown rule: {}", item)),
+            };
+        }
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::iter::from_fn;
+
+    // Brute force checks of single numbers, used to cross-check the counting
+    fn digits_inc_or_same(num: u32) -> bool {
+        let mut it = rev_digit_iter(num);
+        let mut lastd = it.next().unwrap();
+        for d in it {
+            if lastd < d {
+                return false;
+            }
+            lastd = d;
+        }
+        true
+    }
+
+    fn adjacent_digits_same(num: u32) -> bool {
+        let mut it = rev_digit_iter(num);
+        let mut lastd = it.next().unwrap();
+        for d in it {
+            if lastd == d {
+                return true;
+            }
+            lastd = d;
+        }
+        false
+    }
+
+    fn adjacent_digits_same_advanced(num: u32) -> bool {
+        let mut count = 1;
+
+        let mut it = rev_digit_iter(num);
+        let mut lastd = it.next().unwrap();
+        for d in it {
+            if lastd == d {
+                count += 1;
+            } else {
+                if count == 2 {
+                    return true;
+                }
+                count = 1;
+            }
+            lastd = d;
+        }
+
+        if count == 2 {
+            return true;
+        }
+
+        false
+    }
+
+    fn rev_digit_iter(mut num: u32) -> impl Iterator<Item = u32> {
+        from_fn(move || {
+            if num == 0 {
+                None
+            } else {
+                let d = num % 10;
+                num /= 10;
+                Some(d)
+            }
+        })
+    }
+
+    #[test]
+    fn test_digits_inc_or_same() {
+        assert!(digits_inc_or_same(111_111));
+        assert!(!digits_inc_or_same(223_450));
+    }
+
+    #[test]
+    fn test_two_adjacent_digits_same() {
+        assert!(adjacent_digits_same(122_345));
+        assert!(adjacent_digits_same(111_111));
+    }
+
+    #[test]
+    fn test_adjacent_digits_same_advanced() {
+        assert!(adjacent_digits_same_advanced(122_345));
+        assert!(adjacent_digits_same_advanced(111_122));
+        assert!(!adjacent_digits_same_advanced(123_444));
+        assert!(adjacent_digits_same_advanced(112_233));
+        assert!(!adjacent_digits_same_advanced(111_111));
+        assert!(adjacent_digits_same_advanced(112_222));
+    }
+
+    fn brute_force(lo: u32, hi: u32) -> (u64, u64) {
+        let valid = (lo..=hi).filter(|&x| digits_inc_or_same(x));
+        let p1 = valid.clone().filter(|&x| adjacent_digits_same(x)).count();
+        let p2 = valid.filter(|&x| adjacent_digits_same_advanced(x)).count();
+        (p1 as u64, p2 as u64)
+    }
+
+    #[test]
+    fn test_count_passwords() {
+        let ranges = [
+            (138_241, 674_034),
+            (1, 999_999),
+            (111_111, 111_111),
+            (111_112, 111_121),
+            (5, 1_234),
+            (99_999, 100_000),
+            (356_789, 356_799),
+        ];
+        for &(lo, hi) in &ranges {
+            let counted = (
+                Rules::part1().count(lo as u64, hi as u64),
+                Rules::part2().count(lo as u64, hi as u64),
+            );
+            assert_eq!(counted, brute_force(lo, hi), "range {}-{}", lo, hi);
+        }
+    }
+
+    #[test]
+    fn test_long_ranges() {
+        // all 18 digit non-decreasing numbers: choose 18 digits from 1..=9 with repetition
+        let lo = 100_000_000_000_000_000;
+        let hi = 999_999_999_999_999_999;
+        let rules = Rules::new().non_decreasing();
+        let all = rules.clone().run(Run::AtLeast(1)).count(lo, hi);
+        assert_eq!(all, 1_562_275);
+        // at most 9 distinct digits in 18 positions, so some digit repeats
+        assert_eq!(rules.run(Run::AtLeast(2)).count(lo, hi), all);
+
+        // the default rule sets count passwords of every length in the range
+        for rules in &[Rules::part1(), Rules::part2()] {
+            let by_length: u64 = (1..=18).map(|n| rules.clone().digits(n).count(1, hi)).sum();
+            assert_eq!(rules.count(1, hi), by_length);
+            assert!(by_length > rules.clone().digits(6).count(1, hi));
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(Rules::part1().check(111_111), Ok(()));
+        assert_eq!("non-decreasing,run>=2".parse::<Rules>(), Ok(Rules::part1()));
+        assert!("run>2".parse::<Rules>().is_err());
+        assert_eq!(Rules::part1().check(223_450), Err(Rejection::Decreasing(5)));
+        assert_eq!(
+            Rules::part1().check(123_789),
+            Err(Rejection::MissingRun(Run::AtLeast(2)))
+        );
+        assert_eq!(Rules::part2().check(112_233), Ok(()));
+        assert_eq!(
+            Rules::part2().check(123_444),
+            Err(Rejection::MissingRun(Run::Exactly(2)))
+        );
+        assert_eq!(
+            Rules::part2().digits(6).check(11_223),
+            Err(Rejection::DigitCount {
+                expected: 6,
+                found: 5
+            })
+        );
+        let spec = vec!["run>=1"; MAX_RUNS].join(",");
+        let rules: Rules = spec.parse().unwrap();
+        assert_eq!(rules.count(1, 99), 99);
+        assert!(format!("{},run=2", spec).parse::<Rules>().is_err());
+
+        let rules: Rules = "forbid=05,run>=3".parse().unwrap();
+        assert_eq!(rules, Rules::new().forbid(&[0, 5]).run(Run::AtLeast(3)));
+        assert_eq!(rules.check(150), Err(Rejection::ForbiddenDigit(5)));
+        assert_eq!(rules.check(9_119_111), Ok(()));
+    }
+
+    #[test]
+    fn test_count_rules() {
+        // every rule combination agrees with checking each number
+        let rule_sets = vec![
+            Rules::part1(),
+            Rules::part2(),
+            Rules::new().run(Run::Exactly(2)),
+            Rules::new().forbid(&[3, 7]).run(Run::AtLeast(3)),
+            Rules::new()
+                .non_decreasing()
+                .run(Run::Exactly(2))
+                .run(Run::Exactly(3)),
+            Rules::new().digits(4).forbid(&[0]),
+            Rules::new().non_decreasing(),
+        ];
+        for rules in &rule_sets {
+            for &(lo, hi) in &[(0, 20_000), (98_765, 123_456), (554_321, 560_000)] {
+                let checked = (lo..=hi).filter(|x| rules.check(*x).is_ok()).count();
+                assert_eq!(
+                    rules.count(lo, hi),
+                    checked as u64,
+                    "{:?} {}-{}",
+                    rules,
+                    lo,
+                    hi
+                );
+            }
+        }
+    }
+}
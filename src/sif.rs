@@ -0,0 +1,304 @@
+use crate::bitmap::{self, Bitmap, Rgba};
+use std::error::Error;
+use std::fmt;
+
+// Images in the Space Image Format: a sequence of digits, width * height per layer, layers from
+// front to back. A pixel shows the colour of the first layer that is not transparent there.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Pixel {
+    Black,
+    White,
+    Transparent,
+}
+
+impl Pixel {
+    pub fn from_digit(c: char) -> Option<Pixel> {
+        match c {
+            '0' => Some(Pixel::Black),
+            '1' => Some(Pixel::White),
+            '2' => Some(Pixel::Transparent),
+            _ => None,
+        }
+    }
+
+    pub fn rgba(self) -> Rgba {
+        match self {
+            Pixel::Black => bitmap::BLACK,
+            Pixel::White => bitmap::WHITE,
+            Pixel::Transparent => bitmap::TRANSPARENT,
+        }
+    }
+
+    pub fn digit(self) -> char {
+        match self {
+            Pixel::Black => '0',
+            Pixel::White => '1',
+            Pixel::Transparent => '2',
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SifError {
+    EmptyDimensions {
+        width: usize,
+        height: usize,
+    },
+    // The data is empty or ends in the middle of a layer
+    Length {
+        length: usize,
+        layer_size: usize,
+    },
+    // Position counts digits from 0, x and y are within the layer
+    InvalidDigit {
+        position: usize,
+        layer: usize,
+        x: usize,
+        y: usize,
+        found: char,
+    },
+    // A layer given to from_layers that does not have width * height pixels
+    LayerSize {
+        layer: usize,
+        size: usize,
+    },
+}
+
+impl fmt::Display for SifError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SifError::EmptyDimensions { width, height } => {
+                write!(f, "image dimensions {}x{} are empty", width, height)
+            }
+            SifError::Length { length, layer_size } => write!(
+                f,
+                "data length {} is not a positive multiple of the layer size {}",
+                length, layer_size
+            ),
+            SifError::InvalidDigit {
+                position,
+                layer,
+                x,
+                y,
+                found,
+            } => write!(
+                f,
+                "invalid colour {:?} at position {} (layer {}, x {}, y {})",
+                found, position, layer, x, y
+            ),
+            SifError::LayerSize { layer, size } => {
+                write!(f, "layer {} has {} pixels", layer, size)
+            }
+        }
+    }
+}
+
+impl Error for SifError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SifImage {
+    width: usize,
+    height: usize,
+    layers: Vec<Vec<Pixel>>,
+}
+
+impl SifImage {
+    // Decodes the digits, surrounding whitespace is ignored
+    pub fn decode(data: &str, width: usize, height: usize) -> Result<SifImage, SifError> {
+        let layer_size = width * height;
+        if layer_size == 0 {
+            return Err(SifError::EmptyDimensions { width, height });
+        }
+        let data = data.trim();
+        let length = data.chars().count();
+        if length == 0 || length % layer_size != 0 {
+            return Err(SifError::Length { length, layer_size });
+        }
+
+        let mut pixels = Vec::with_capacity(length);
+        for (position, c) in data.chars().enumerate() {
+            let pixel = Pixel::from_digit(c).ok_or(SifError::InvalidDigit {
+                position,
+                layer: position / layer_size,
+                x: position % width,
+                y: position % layer_size / width,
+                found: c,
+            })?;
+            pixels.push(pixel);
+        }
+        Ok(SifImage {
+            width,
+            height,
+            layers: pixels.chunks(layer_size).map(|l| l.to_vec()).collect(),
+        })
+    }
+
+    // Builds an image from layers given front to back, each in row-major order
+    pub fn from_layers(
+        width: usize,
+        height: usize,
+        layers: Vec<Vec<Pixel>>,
+    ) -> Result<SifImage, SifError> {
+        if width * height == 0 {
+            return Err(SifError::EmptyDimensions { width, height });
+        }
+        if layers.is_empty() {
+            return Err(SifError::Length {
+                length: 0,
+                layer_size: width * height,
+            });
+        }
+        if let Some((layer, pixels)) = layers
+            .iter()
+            .enumerate()
+            .find(|(_, l)| l.len() != width * height)
+        {
+            return Err(SifError::LayerSize {
+                layer,
+                size: pixels.len(),
+            });
+        }
+        Ok(SifImage {
+            width,
+            height,
+            layers,
+        })
+    }
+
+    // The digits of all layers, the inverse of decode
+    pub fn encode(&self) -> String {
+        self.layers.iter().flatten().map(|p| p.digit()).collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn layers(&self) -> &[Vec<Pixel>] {
+        &self.layers
+    }
+
+    // Number of ones times number of twos in the layer with the fewest zeros, to check for
+    // corrupted transmissions
+    pub fn checksum(&self) -> usize {
+        let count = |layer: &[Pixel], pixel| layer.iter().filter(|p| **p == pixel).count();
+        let layer = self
+            .layers
+            .iter()
+            .min_by_key(|l| count(l, Pixel::Black))
+            .unwrap();
+        count(layer, Pixel::White) * count(layer, Pixel::Transparent)
+    }
+
+    // The visible pixels in row-major order; pixels transparent in all layers stay transparent
+    pub fn render(&self) -> Vec<Pixel> {
+        let mut image = vec![Pixel::Transparent; self.width * self.height];
+        for layer in &self.layers {
+            for (pixel, layer_pixel) in image.iter_mut().zip(layer) {
+                if *pixel == Pixel::Transparent {
+                    *pixel = *layer_pixel;
+                }
+            }
+        }
+        image
+    }
+
+    pub fn to_bitmap(&self) -> Bitmap {
+        let pixels = self.render();
+        Bitmap::from_fn(self.width, self.height, |x, y| {
+            pixels[y * self.width + x].rgba()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Pixel::*;
+
+    #[test]
+    fn test_decode() {
+        let image = SifImage::decode("123456789012\n", 3, 2);
+        assert_eq!(
+            image,
+            Err(SifError::InvalidDigit {
+                position: 2,
+                layer: 0,
+                x: 2,
+                y: 0,
+                found: '3'
+            })
+        );
+        let image = SifImage::decode("0222112222120000", 2, 2).unwrap();
+        assert_eq!(image.layers().len(), 4);
+        assert_eq!(image.render(), vec![Black, White, White, Black]);
+        assert_eq!(image.checksum(), 4);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(
+            SifImage::decode("01210", 2, 2),
+            Err(SifError::Length {
+                length: 5,
+                layer_size: 4
+            })
+        );
+        assert_eq!(
+            SifImage::decode("", 2, 2),
+            Err(SifError::Length {
+                length: 0,
+                layer_size: 4
+            })
+        );
+        assert_eq!(
+            SifImage::decode("0000", 0, 4),
+            Err(SifError::EmptyDimensions {
+                width: 0,
+                height: 4
+            })
+        );
+        assert_eq!(
+            SifImage::decode("00001x", 2, 1).unwrap_err().to_string(),
+            "invalid colour 'x' at position 5 (layer 2, x 1, y 0)"
+        );
+        assert_eq!(
+            SifImage::from_layers(2, 1, vec![vec![Black, White], vec![Black]]),
+            Err(SifError::LayerSize { layer: 1, size: 1 })
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = "0222112222120000";
+        assert_eq!(SifImage::decode(data, 2, 2).unwrap().encode(), data);
+
+        let layers = vec![
+            vec![
+                Transparent,
+                White,
+                Transparent,
+                Black,
+                Transparent,
+                Transparent,
+            ],
+            vec![Black, Black, Transparent, White, White, Transparent],
+            vec![White, Black, White, Black, White, Black],
+        ];
+        let image = SifImage::from_layers(3, 2, layers).unwrap();
+        assert_eq!(image.encode(), "212022002112101010");
+        assert_eq!(SifImage::decode(&image.encode(), 3, 2), Ok(image.clone()));
+        assert_eq!(
+            image.render(),
+            vec![Black, White, White, Black, White, Black]
+        );
+        let bitmap = image.to_bitmap();
+        assert_eq!(bitmap.get(1, 0), bitmap::WHITE);
+        assert_eq!(bitmap.get(2, 1), bitmap::BLACK);
+    }
+}
@@ -0,0 +1,418 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+// Wires are stored as axis parallel segments. Crossings between horizontal and vertical
+// segments are found with a sweep over x, overlapping segments on the same line are compared
+// per line. Points where a wire crosses itself and the shared origin are not crossings.
+
+pub type Point = (i64, i64);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+    // Steps taken along the wire before the start of the segment
+    pub steps: u64,
+}
+
+impl Segment {
+    fn is_horizontal(&self) -> bool {
+        self.start.1 == self.end.1
+    }
+
+    fn x_range(&self) -> (i64, i64) {
+        (self.start.0.min(self.end.0), self.start.0.max(self.end.0))
+    }
+
+    fn y_range(&self) -> (i64, i64) {
+        (self.start.1.min(self.end.1), self.start.1.max(self.end.1))
+    }
+
+    // Steps along the wire to reach a point on the segment
+    fn steps_to(&self, point: Point) -> u64 {
+        self.steps + manhattan(self.start, point)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wire {
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    Direction,
+    Length,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WireError {
+    // Line of the wire in the input and position of the move in the path, both from 1
+    pub line: usize,
+    pub index: usize,
+    pub token: String,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            ErrorKind::Direction => "direction",
+            ErrorKind::Length => "length",
+        };
+        write!(
+            f,
+            "invalid {} in move {} {:?} of wire in line {}",
+            what, self.index, self.token, self.line
+        )
+    }
+}
+
+impl Error for WireError {}
+
+impl Wire {
+    // Parses a comma separated path like R8,U5,L5,D3 starting at the origin
+    pub fn parse(path: &str) -> Result<Wire, WireError> {
+        let mut segments = vec![];
+        let mut pos = (0, 0);
+        let mut steps = 0;
+        for (i, token) in path.trim().split(',').enumerate() {
+            let token = token.trim();
+            let error = |kind| WireError {
+                line: 1,
+                index: i + 1,
+                token: token.to_string(),
+                kind,
+            };
+            let (dx, dy) = match token.get(..1) {
+                Some("L") => (-1, 0),
+                Some("U") => (0, 1),
+                Some("R") => (1, 0),
+                Some("D") => (0, -1),
+                _ => return Err(error(ErrorKind::Direction)),
+            };
+            let len = token[1..]
+                .parse::<u32>()
+                .map_err(|_| error(ErrorKind::Length))?;
+            let end = (pos.0 + dx * len as i64, pos.1 + dy * len as i64);
+            segments.push(Segment {
+                start: pos,
+                end,
+                steps,
+            });
+            pos = end;
+            steps += len as u64;
+        }
+        Ok(Wire { segments })
+    }
+}
+
+// Parses one wire per line, empty lines are skipped
+pub fn parse_wires(input: &str) -> Result<Vec<Wire>, WireError> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| Wire::parse(line).map_err(|e| WireError { line: i + 1, ..e }))
+        .collect()
+}
+
+pub fn manhattan(a: Point, b: Point) -> u64 {
+    ((a.0 - b.0).abs() + (a.1 - b.1).abs()) as u64
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crossing {
+    pub point: Point,
+    // Indices of the two wires, the lower first
+    pub wires: (usize, usize),
+    // Manhattan distance from the origin
+    pub distance: u64,
+    // Combined steps of both wires to their first visit of the point
+    pub delay: u64,
+}
+
+// Collects crossings keyed by wire pair and point, keeping the lowest steps per wire
+#[derive(Default)]
+struct Collector {
+    found: BTreeMap<((usize, usize), Point), (u64, u64)>,
+}
+
+impl Collector {
+    fn add(&mut self, point: Point, a: (usize, &Segment), b: (usize, &Segment)) {
+        if point == (0, 0) || a.0 == b.0 {
+            return;
+        }
+        let (a, b) = if a.0 < b.0 { (a, b) } else { (b, a) };
+        let steps = (a.1.steps_to(point), b.1.steps_to(point));
+        let entry = self.found.entry(((a.0, b.0), point)).or_insert(steps);
+        entry.0 = entry.0.min(steps.0);
+        entry.1 = entry.1.min(steps.1);
+    }
+
+    fn finish(self) -> Vec<Crossing> {
+        self.found
+            .into_iter()
+            .map(|((wires, point), steps)| Crossing {
+                point,
+                wires,
+                distance: manhattan((0, 0), point),
+                delay: steps.0 + steps.1,
+            })
+            .collect()
+    }
+}
+
+// Finds all crossings between any two of the wires, ordered by wire pair and point
+pub fn crossings(wires: &[Wire]) -> Vec<Crossing> {
+    let segments: Vec<(usize, &Segment)> = wires
+        .iter()
+        .enumerate()
+        .flat_map(|(i, wire)| wire.segments.iter().map(move |s| (i, s)))
+        .filter(|(_, s)| s.start != s.end)
+        .collect();
+    let mut collector = Collector::default();
+    sweep(&segments, &mut collector);
+    overlaps(&segments, &mut collector);
+    collector.finish()
+}
+
+// Horizontal segments enter and leave the active set at their x range, vertical segments query
+// the active set for their y range. Entries are handled before queries and queries before
+// exits, so crossings at segment ends are found.
+fn sweep(segments: &[(usize, &Segment)], collector: &mut Collector) {
+    let mut events = vec![];
+    for (id, (_, segment)) in segments.iter().enumerate() {
+        if segment.is_horizontal() {
+            let (x0, x1) = segment.x_range();
+            events.push((x0, 0, id));
+            events.push((x1, 2, id));
+        } else {
+            events.push((segment.start.0, 1, id));
+        }
+    }
+    events.sort_unstable();
+
+    let mut active: BTreeMap<i64, Vec<usize>> = BTreeMap::new();
+    for (x, kind, id) in events {
+        let y = segments[id].1.start.1;
+        match kind {
+            0 => active.entry(y).or_default().push(id),
+            1 => {
+                let (y0, y1) = segments[id].1.y_range();
+                for (y, horizontals) in active.range(y0..=y1) {
+                    for h in horizontals {
+                        collector.add((x, *y), segments[*h], segments[id]);
+                    }
+                }
+            }
+            _ => {
+                let ids = active.get_mut(&y).unwrap();
+                ids.retain(|h| *h != id);
+                if ids.is_empty() {
+                    active.remove(&y);
+                }
+            }
+        }
+    }
+}
+
+// Segments of different wires lying on the same line cross at every point they share
+fn overlaps(segments: &[(usize, &Segment)], collector: &mut Collector) {
+    let mut lines = HashMap::new();
+    for (id, (_, segment)) in segments.iter().enumerate() {
+        let key = if segment.is_horizontal() {
+            (true, segment.start.1)
+        } else {
+            (false, segment.start.0)
+        };
+        let (lo, hi) = if key.0 {
+            segment.x_range()
+        } else {
+            segment.y_range()
+        };
+        lines.entry(key).or_insert_with(Vec::new).push((lo, hi, id));
+    }
+    for ((horizontal, line), mut ranges) in lines {
+        ranges.sort_unstable();
+        for (i, (lo, hi, a)) in ranges.iter().enumerate() {
+            for (lo2, hi2, b) in ranges[i + 1..].iter().take_while(|r| r.0 <= *hi) {
+                for pos in *lo.max(lo2)..=*hi.min(hi2) {
+                    let point = if horizontal { (pos, line) } else { (line, pos) };
+                    collector.add(point, segments[*a], segments[*b]);
+                }
+            }
+        }
+    }
+}
+
+const COLOURS: [&str; 6] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#9467bd", "#8c564b", "#e377c2",
+];
+
+// Writes an SVG drawing of the wires with y pointing up. Every wire gets its own colour, the
+// origin is a black dot, crossings are grey dots and the crossings closest by distance and by
+// delay are circled in red and blue.
+pub fn write_svg<W: Write>(wires: &[Wire], crossings: &[Crossing], out: &mut W) -> io::Result<()> {
+    let points = wires
+        .iter()
+        .flat_map(|wire| wire.segments.iter().map(|s| s.end))
+        .chain(Some((0, 0)));
+    let (mut x0, mut y0, mut x1, mut y1) = (0, 0, 0, 0);
+    for (x, y) in points {
+        x0 = x0.min(x);
+        x1 = x1.max(x);
+        y0 = y0.min(-y);
+        y1 = y1.max(-y);
+    }
+    let size = (x1 - x0).max(y1 - y0).max(1);
+    let unit = (size as f64 / 400.0).max(0.1);
+    let margin = unit * 10.0;
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.2} {:.2} {:.2} {:.2}">"#,
+        x0 as f64 - margin,
+        y0 as f64 - margin,
+        (x1 - x0) as f64 + 2.0 * margin,
+        (y1 - y0) as f64 + 2.0 * margin
+    )?;
+
+    for (i, wire) in wires.iter().enumerate() {
+        let mut path = String::from("M 0 0");
+        for segment in &wire.segments {
+            path += &format!(" L {} {}", segment.end.0, -segment.end.1);
+        }
+        writeln!(
+            out,
+            r#"  <path d="{}" fill="none" stroke="{}" stroke-width="{:.2}"/>"#,
+            path,
+            COLOURS[i % COLOURS.len()],
+            unit
+        )?;
+    }
+
+    let circle = |out: &mut W, point: Point, r: f64, style: &str| {
+        writeln!(
+            out,
+            r#"  <circle cx="{}" cy="{}" r="{:.2}" {}/>"#,
+            point.0,
+            -point.1,
+            r * unit,
+            style
+        )
+    };
+    for crossing in crossings {
+        circle(out, crossing.point, 2.0, r#"fill="grey""#)?;
+    }
+    circle(out, (0, 0), 3.0, r#"fill="black""#)?;
+    let closest = [
+        (crossings.iter().min_by_key(|c| c.distance), "red"),
+        (crossings.iter().min_by_key(|c| c.delay), "blue"),
+    ];
+    for (crossing, colour) in closest.iter() {
+        if let Some(crossing) = crossing {
+            let style = format!(
+                r#"fill="none" stroke="{}" stroke-width="{:.2}""#,
+                colour, unit
+            );
+            circle(out, crossing.point, 6.0, &style)?;
+        }
+    }
+    writeln!(out, "</svg>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closest(input: &str) -> (u64, u64) {
+        let crossings = crossings(&parse_wires(input).unwrap());
+        (
+            crossings.iter().map(|c| c.distance).min().unwrap(),
+            crossings.iter().map(|c| c.delay).min().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(closest("R8,U5,L5,D3\nU7,R6,D4,L4\n"), (6, 30));
+        assert_eq!(
+            closest("R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83\n"),
+            (159, 610)
+        );
+        assert_eq!(
+            closest(
+                "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\n\
+                 U98,R91,D20,R16,D67,R40,U7,R15,U6,R7\n"
+            ),
+            (135, 410)
+        );
+    }
+
+    #[test]
+    fn test_crossings() {
+        let wires = parse_wires("R8,U5,L5,D3\nU7,R6,D4,L4\n").unwrap();
+        let found = crossings(&wires);
+        assert_eq!(
+            found.iter().map(|c| c.point).collect::<Vec<_>>(),
+            vec![(3, 3), (6, 5)]
+        );
+        assert_eq!(found[0].wires, (0, 1));
+        assert_eq!(found[1].delay, 30);
+    }
+
+    #[test]
+    fn test_overlap_and_more_wires() {
+        // the first two wires share x = 2..3 on y = 0, the third crosses both
+        let wires = parse_wires("R3\nU1,R2,D1,R5\nR2,U2,D4").unwrap();
+        let found = crossings(&wires);
+        let points = |pair| {
+            found
+                .iter()
+                .filter(|c| c.wires == pair)
+                .map(|c| c.point)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(points((0, 1)), vec![(2, 0), (3, 0)]);
+        assert_eq!(points((0, 2)), vec![(1, 0), (2, 0)]);
+        assert_eq!(points((1, 2)), vec![(2, 0), (2, 1)]);
+        // the third wire visits (2, 0) after 2 and after 6 steps
+        let c = found
+            .iter()
+            .find(|c| c.wires == (1, 2) && c.point == (2, 0));
+        assert_eq!(c.unwrap().delay, 4 + 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse_wires("R8,U5\n\nR2,X3\n"),
+            Err(WireError {
+                line: 3,
+                index: 2,
+                token: "X3".to_string(),
+                kind: ErrorKind::Direction
+            })
+        );
+        assert_eq!(Wire::parse("R").unwrap_err().kind, ErrorKind::Length);
+        assert_eq!(Wire::parse("U-4").unwrap_err().kind, ErrorKind::Length);
+    }
+
+    #[test]
+    fn test_svg() {
+        let wires = parse_wires("R8,U5,L5,D3\nU7,R6,D4,L4\n").unwrap();
+        let mut svg = vec![];
+        write_svg(&wires, &crossings(&wires), &mut svg).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(r#"d="M 0 0 L 8 0 L 8 -5 L 3 -5 L 3 -2""#));
+        assert!(svg.contains(r##"stroke="#ff7f0e""##));
+        // two crossings, the origin and the two closest crossings
+        assert_eq!(svg.matches("<circle").count(), 5);
+        assert!(svg.contains(r#"<circle cx="3" cy="-3" r="0.60" fill="none" stroke="red""#));
+        assert!(svg.contains(r#"<circle cx="6" cy="-5" r="0.60" fill="none" stroke="blue""#));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
@@ -0,0 +1,185 @@
+use aoc2019::intcode::gdbstub::GdbStub;
+use aoc2019::intcode::IntCodeCpu;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+
+// Outputs 1 if the input equals 8, else 0
+const CODE: &str = "3,9,8,9,10,9,4,9,99,-1,8";
+
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    console: String,
+}
+
+impl Client {
+    fn read_packet(&mut self) -> String {
+        let mut byte = [0];
+        loop {
+            self.reader.read_exact(&mut byte).unwrap();
+            if byte[0] == b'$' {
+                break;
+            }
+        }
+        let mut data = vec![];
+        self.reader.read_until(b'#', &mut data).unwrap();
+        data.pop();
+        let mut checksum = [0; 2];
+        self.reader.read_exact(&mut checksum).unwrap();
+        let sum = data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        assert_eq!(String::from_utf8_lossy(&checksum), format!("{:02x}", sum));
+        self.writer.write_all(b"+").unwrap();
+        String::from_utf8(data).unwrap()
+    }
+
+    fn send(&mut self, data: &str) {
+        let sum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(self.writer, "${}#{:02x}", data, sum).unwrap();
+        let mut ack = [0];
+        self.reader.read_exact(&mut ack).unwrap();
+        assert_eq!(ack[0], b'+');
+    }
+
+    // Sends a command and returns the reply, collecting console output on the way
+    fn command(&mut self, data: &str) -> String {
+        self.send(data);
+        loop {
+            let reply = self.read_packet();
+            if reply.starts_with('O') && reply != "OK" {
+                let bytes: Vec<u8> = (1..reply.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&reply[i..i + 2], 16).unwrap())
+                    .collect();
+                self.console.push_str(&String::from_utf8(bytes).unwrap());
+            } else {
+                return reply;
+            }
+        }
+    }
+}
+
+fn connect(cpu: IntCodeCpu) -> (Client, JoinHandle<IntCodeCpu>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut stub = GdbStub::new(cpu);
+        stub.serve(stream).unwrap();
+        stub.into_cpu()
+    });
+    let stream = TcpStream::connect(addr).unwrap();
+    let client = Client {
+        reader: BufReader::new(stream.try_clone().unwrap()),
+        writer: stream,
+        console: String::new(),
+    };
+    (client, server)
+}
+
+#[test]
+fn test_registers_and_step() {
+    let mut cpu = IntCodeCpu::from_code(CODE);
+    cpu.input.push_back(8);
+    let (mut client, server) = connect(cpu);
+
+    assert_eq!(
+        client.command("qSupported:multiprocess+"),
+        "PacketSize=1000"
+    );
+    assert_eq!(client.command("?"), "S05");
+    assert_eq!(client.command("g"), "0".repeat(32));
+    assert_eq!(client.command("s"), "S05");
+    // ip is a byte address, cell 2 = byte 0x10
+    assert_eq!(client.command("p0"), "1000000000000000");
+    assert_eq!(client.command("P1=0500000000000000"), "OK");
+    assert_eq!(
+        client.command("g"),
+        format!("{}{}", "10", "0".repeat(14)) + "05" + &"0".repeat(14)
+    );
+    assert_eq!(client.command("p2"), "E01");
+    assert_eq!(client.command("P0=0300000000000000"), "E01");
+    assert_eq!(client.command(&format!("G{}", "0".repeat(32))), "OK");
+    assert_eq!(client.command("p0"), "0".repeat(16));
+    assert_eq!(client.command("s4"), "E01");
+    // steps EQ at cell 2 to OUT at cell 6
+    assert_eq!(client.command("s10"), "S05");
+    assert_eq!(client.command("p0"), "3000000000000000");
+    client.send("k");
+
+    // the input 8 was compared to 8
+    let cpu = server.join().unwrap();
+    assert_eq!(cpu.memory()[9], 1);
+}
+
+#[test]
+fn test_breakpoints_and_memory() {
+    let mut cpu = IntCodeCpu::from_code(CODE);
+    cpu.input.push_back(8);
+    let (mut client, server) = connect(cpu);
+
+    // break on OUT at cell 6 = byte 0x30
+    assert_eq!(client.command("Z0,30,1"), "OK");
+    assert_eq!(client.command("c"), "S05");
+    assert_eq!(client.command("p0"), "3000000000000000");
+    assert_eq!(client.command("Z0,31,1"), "E01");
+
+    // cell 9 = byte 0x48 holds the comparison result
+    assert_eq!(client.command("m48,8"), "0100000000000000");
+    assert_eq!(client.command("M48,1:07"), "OK");
+    assert_eq!(client.command("m48,2"), "0700");
+
+    assert_eq!(client.command("z0,30,1"), "OK");
+    assert_eq!(client.command("c"), "W00");
+    assert_eq!(client.console, "7\n");
+    assert_eq!(client.command("D"), "OK");
+
+    let cpu = server.join().unwrap();
+    assert!(!cpu.running());
+}
+
+#[test]
+fn test_input_required() {
+    let (mut client, server) = connect(IntCodeCpu::from_code(CODE));
+    assert_eq!(client.command("c"), "S05");
+    assert_eq!(client.command("p0"), "0000000000000000");
+    assert_eq!(client.command("vMustReplyEmpty"), "");
+    client.send("k");
+    server.join().unwrap();
+}
+
+#[test]
+fn test_faults() {
+    // ADD with an immediate destination
+    let (mut client, server) = connect(IntCodeCpu::from_code("11101,1,1,3,99"));
+    assert_eq!(client.command("s"), "S04");
+    assert_eq!(client.command("p0"), "0".repeat(16));
+    // ADD reading from position -1
+    assert_eq!(
+        client.command("M0,10:0100000000000000ffffffffffffffff"),
+        "OK"
+    );
+    assert_eq!(client.command("c"), "S0b");
+    // memory past the program reads as 0, long reads are cut short
+    assert_eq!(client.command("m1000,8"), "00".repeat(8));
+    assert_eq!(client.command("m0,100000").len(), 2 * 0x1000);
+    // accesses that wrap or lie far outside of memory are refused
+    assert_eq!(client.command("mfffffffffffffff8,10"), "E01");
+    assert_eq!(client.command("mffffffffffffffff,1"), "E01");
+    assert_eq!(client.command("m1000000,8"), "E01");
+    assert_eq!(client.command("Mfffffffffffffff8,1:00"), "E01");
+    assert_eq!(
+        client.command("M7ffffffffffffff8,8:0100000000000000"),
+        "E01"
+    );
+    assert_eq!(client.command("Mfffff8,8:0100000000000000"), "OK");
+    assert_eq!(client.command("mfffff8,8"), "0100000000000000");
+    client.send("k");
+    server.join().unwrap();
+
+    // ADD with its operands past the end of memory
+    let (mut client, server) = connect(IntCodeCpu::from_code("1,0"));
+    assert_eq!(client.command("s"), "S0b");
+    client.send("k");
+    server.join().unwrap();
+}